@@ -0,0 +1,425 @@
+// src/columnar.rs
+
+use crate::decode::Array;
+use crate::output::ResultSink;
+use crate::value::{ColumnMeta, ColumnType, Precision, TimestampFormat, Value};
+use anyhow::{anyhow, bail, Result};
+use arrow_array::builder::{
+    BinaryBuilder, BooleanBuilder, Date32Builder, FixedSizeBinaryBuilder, Float32Builder,
+    Float64Builder, Int16Builder, Int32Builder, Int64Builder, StringBuilder,
+    StringDictionaryBuilder, TimestampMicrosecondBuilder, TimestampNanosecondBuilder,
+};
+use arrow_array::types::Int32Type;
+use arrow_array::{ArrayRef, Float64Array, ListArray, RecordBatch};
+use arrow_buffer::{NullBuffer, OffsetBuffer};
+use arrow_ipc::writer::{FileWriter, StreamWriter};
+use arrow_schema::{DataType, Field, Schema, SchemaRef, TimeUnit};
+use chrono::NaiveDate;
+use parquet::arrow::ArrowWriter;
+use parquet::basic::Compression;
+use parquet::file::properties::WriterProperties;
+use std::io::Write;
+use std::sync::Arc;
+
+/// Rows converted into one Arrow record batch at a time.
+const BATCH_ROWS: usize = 8192;
+
+/// Rows per Parquet row group. The Parquet writer holds one row group in
+/// memory; Arrow IPC writes every batch straight through.
+const ROW_GROUP_ROWS: usize = 128 * 1024;
+
+/// Buffers up to `BATCH_ROWS` rows and converts them into Arrow record
+/// batches, deriving the Arrow schema from the QuestDB column types.
+struct BatchBuilder {
+    columns: Vec<ColumnMeta>,
+    timestamps: TimestampFormat,
+    rows: Vec<Vec<Value>>,
+    schema: Option<SchemaRef>,
+    /// Whether SYMBOL columns are dictionary-encoded.
+    dictionaries: bool,
+}
+
+impl BatchBuilder {
+    fn new(columns: &[ColumnMeta], timestamps: TimestampFormat, dictionaries: bool) -> Self {
+        Self {
+            columns: columns.to_vec(),
+            timestamps,
+            rows: Vec::with_capacity(BATCH_ROWS),
+            schema: None,
+            dictionaries,
+        }
+    }
+
+    fn push(&mut self, values: &[Value]) {
+        self.rows.push(values.to_vec());
+    }
+
+    fn is_full(&self) -> bool {
+        self.rows.len() >= BATCH_ROWS
+    }
+
+    fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+
+    /// Returns the schema, fixing it from the buffered rows on first use.
+    ///
+    /// PGWire doesn't say how many dimensions an array column has, so the
+    /// nesting depth is taken from the first non-null array in the result.
+    fn schema(&mut self) -> SchemaRef {
+        if let Some(schema) = &self.schema {
+            return schema.clone();
+        }
+        let fields: Vec<Field> = self
+            .columns
+            .iter()
+            .enumerate()
+            .map(|(i, col)| {
+                let depth = self
+                    .rows
+                    .iter()
+                    .find_map(|row| match row.get(i) {
+                        Some(Value::Array(array)) => Some(array.dims.len().max(1)),
+                        _ => None,
+                    })
+                    .unwrap_or(1);
+                let data_type = arrow_type(col.column_type, depth, &self.timestamps, self.dictionaries);
+                Field::new(&col.name, data_type, true)
+            })
+            .collect();
+        let schema = Arc::new(Schema::new(fields));
+        self.schema = Some(schema.clone());
+        schema
+    }
+
+    /// Converts the buffered rows into a record batch and clears the buffer.
+    fn finish(&mut self) -> Result<RecordBatch> {
+        let schema = self.schema();
+        let rows = std::mem::take(&mut self.rows);
+        let arrays = schema
+            .fields()
+            .iter()
+            .enumerate()
+            .map(|(i, field)| {
+                let values: Vec<&Value> = rows.iter().map(|row| row.get(i).unwrap_or(&Value::Null)).collect();
+                build_column(field.data_type(), &values, &self.timestamps)
+                    .map_err(|e| anyhow!("column '{}': {}", field.name(), e))
+            })
+            .collect::<Result<Vec<ArrayRef>>>()?;
+        Ok(RecordBatch::try_new(schema, arrays)?)
+    }
+}
+
+/// Maps a QuestDB column type onto the Arrow type used to store it.
+///
+/// Timestamps carry the configured time zone so readers display them the
+/// same way the text formats do; `ns` precision switches to nanosecond units.
+/// SYMBOL columns become `Dictionary(Int32, Utf8)` when `dictionaries` is
+/// set. Only HTTP results report SYMBOL; over PGWire they stay `Utf8`.
+fn arrow_type(
+    column_type: ColumnType,
+    array_depth: usize,
+    timestamps: &TimestampFormat,
+    dictionaries: bool,
+) -> DataType {
+    match column_type {
+        ColumnType::Boolean => DataType::Boolean,
+        ColumnType::Short => DataType::Int16,
+        ColumnType::Int => DataType::Int32,
+        ColumnType::Long => DataType::Int64,
+        ColumnType::Float => DataType::Float32,
+        ColumnType::Double => DataType::Float64,
+        ColumnType::Timestamp => {
+            let unit = match timestamps.precision {
+                Precision::Nanos => TimeUnit::Nanosecond,
+                _ => TimeUnit::Microsecond,
+            };
+            DataType::Timestamp(unit, Some(timestamps.timezone.name().into()))
+        }
+        ColumnType::Date => DataType::Date32,
+        ColumnType::Uuid => DataType::FixedSizeBinary(16),
+        ColumnType::Binary => DataType::Binary,
+        ColumnType::DoubleArray => (0..array_depth).fold(DataType::Float64, |inner, _| {
+            DataType::List(Arc::new(Field::new("item", inner, true)))
+        }),
+        ColumnType::Symbol if dictionaries => {
+            DataType::Dictionary(Box::new(DataType::Int32), Box::new(DataType::Utf8))
+        }
+        ColumnType::Char | ColumnType::Varchar | ColumnType::Symbol | ColumnType::Long256 | ColumnType::Interval => {
+            DataType::Utf8
+        }
+    }
+}
+
+fn mismatch(value: &Value, data_type: &DataType) -> anyhow::Error {
+    anyhow!("cannot store {:?} as {}", value, data_type)
+}
+
+fn build_column(data_type: &DataType, values: &[&Value], timestamps: &TimestampFormat) -> Result<ArrayRef> {
+    macro_rules! build {
+        ($builder:expr, $($pat:pat => $val:expr),+) => {{
+            let mut builder = $builder;
+            for value in values {
+                match value {
+                    Value::Null => builder.append_null(),
+                    $($pat => builder.append_value($val),)+
+                    other => return Err(mismatch(other, data_type)),
+                }
+            }
+            Arc::new(builder.finish()) as ArrayRef
+        }};
+    }
+
+    let array = match data_type {
+        DataType::Boolean => build!(BooleanBuilder::new(), Value::Bool(b) => *b),
+        DataType::Int16 => build!(Int16Builder::new(), Value::Int(i) => i16::try_from(*i)?),
+        DataType::Int32 => build!(Int32Builder::new(), Value::Int(i) => i32::try_from(*i)?),
+        DataType::Int64 => build!(Int64Builder::new(), Value::Int(i) => *i),
+        DataType::Float32 => build!(Float32Builder::new(), Value::Float(f) => *f),
+        DataType::Float64 => build!(Float64Builder::new(), Value::Double(f) => *f),
+        DataType::Timestamp(TimeUnit::Nanosecond, tz) => build!(
+            TimestampNanosecondBuilder::new().with_timezone_opt(tz.clone()),
+            Value::Timestamp(ts) => ts
+                .and_utc()
+                .timestamp_nanos_opt()
+                .ok_or_else(|| anyhow!("timestamp {} out of nanosecond range", ts))?
+        ),
+        DataType::Timestamp(_, tz) => build!(
+            TimestampMicrosecondBuilder::new().with_timezone_opt(tz.clone()),
+            Value::Timestamp(ts) => ts.and_utc().timestamp_micros()
+        ),
+        DataType::Date32 => build!(
+            Date32Builder::new(),
+            Value::Date(d) => d.signed_duration_since(unix_epoch()).num_days() as i32
+        ),
+        DataType::FixedSizeBinary(16) => {
+            let mut builder = FixedSizeBinaryBuilder::new(16);
+            for value in values {
+                match value {
+                    Value::Null => builder.append_null(),
+                    Value::Uuid(u) => builder.append_value(u.to_be_bytes())?,
+                    other => return Err(mismatch(other, data_type)),
+                }
+            }
+            Arc::new(builder.finish())
+        }
+        DataType::Binary => build!(BinaryBuilder::new(), Value::Binary(b) => b),
+        DataType::Utf8 => {
+            let mut builder = StringBuilder::new();
+            for value in values {
+                match value {
+                    Value::Null => builder.append_null(),
+                    other => builder.append_value(other.render(timestamps)),
+                }
+            }
+            Arc::new(builder.finish())
+        }
+        DataType::Dictionary(key, value) if **key == DataType::Int32 && **value == DataType::Utf8 => {
+            let mut builder = StringDictionaryBuilder::<Int32Type>::new();
+            for value in values {
+                match value {
+                    Value::Null => builder.append_null(),
+                    other => builder.append_value(other.render(timestamps)),
+                }
+            }
+            Arc::new(builder.finish())
+        }
+        DataType::List(_) => {
+            let arrays = values
+                .iter()
+                .map(|value| match value {
+                    Value::Null => Ok(None),
+                    Value::Array(array) => Ok(Some(array)),
+                    other => Err(mismatch(other, data_type)),
+                })
+                .collect::<Result<Vec<Option<&Array>>>>()?;
+            build_list(data_type, &arrays)?
+        }
+        other => bail!("unsupported Arrow type {}", other),
+    };
+    Ok(array)
+}
+
+/// Builds a (nested) list column from multi-dimensional arrays.
+fn build_list(data_type: &DataType, arrays: &[Option<&Array>]) -> Result<ArrayRef> {
+    let mut fields = Vec::new();
+    let mut inner = data_type;
+    while let DataType::List(field) = inner {
+        fields.push(field.clone());
+        inner = field.data_type();
+    }
+    let depth = fields.len();
+
+    // Lengths of the lists at each nesting level, outermost first.
+    let mut lengths: Vec<Vec<usize>> = vec![Vec::new(); depth];
+    let mut leaves = Vec::new();
+    for array in arrays {
+        let Some(array) = array else {
+            lengths[0].push(0);
+            continue;
+        };
+        let dims: Vec<usize> = if array.dims.is_empty() { vec![0; depth] } else { array.dims.clone() };
+        if dims.len() != depth {
+            bail!("expected {}-dimensional array, got {} dimensions", depth, dims.len());
+        }
+        let mut lists = 1;
+        for (level, &dim) in dims.iter().enumerate() {
+            lengths[level].extend(std::iter::repeat_n(dim, lists));
+            lists *= dim;
+        }
+        leaves.extend(array.elems.iter().copied());
+    }
+
+    let mut child: ArrayRef = Arc::new(Float64Array::from(leaves));
+    for level in (0..depth).rev() {
+        let nulls = (level == 0).then(|| NullBuffer::from_iter(arrays.iter().map(Option::is_some)));
+        let offsets = OffsetBuffer::from_lengths(lengths[level].iter().copied());
+        child = Arc::new(ListArray::try_new(fields[level].clone(), offsets, child, nulls)?);
+    }
+    Ok(child)
+}
+
+fn unix_epoch() -> NaiveDate {
+    NaiveDate::from_ymd_opt(1970, 1, 1).expect("valid epoch")
+}
+
+/// File formats written by `ColumnarSink`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColumnarFormat {
+    /// Parquet file, one row group at a time.
+    Parquet,
+    /// Arrow IPC stream, one message per record batch.
+    ArrowStream,
+    /// Arrow IPC file (Feather v2), readable with random access.
+    ArrowFile,
+}
+
+/// A writer that record batches are appended to.
+trait BatchWriter: Send {
+    fn write(&mut self, batch: &RecordBatch) -> Result<()>;
+    /// Writes any footer and flushes the underlying output.
+    fn finish(self: Box<Self>) -> Result<()>;
+}
+
+impl BatchWriter for ArrowWriter<Box<dyn Write + Send>> {
+    fn write(&mut self, batch: &RecordBatch) -> Result<()> {
+        ArrowWriter::write(self, batch)?;
+        Ok(())
+    }
+
+    fn finish(self: Box<Self>) -> Result<()> {
+        self.into_inner()?.flush()?;
+        Ok(())
+    }
+}
+
+impl BatchWriter for StreamWriter<Box<dyn Write + Send>> {
+    fn write(&mut self, batch: &RecordBatch) -> Result<()> {
+        StreamWriter::write(self, batch)?;
+        Ok(())
+    }
+
+    fn finish(self: Box<Self>) -> Result<()> {
+        self.into_inner()?.flush()?;
+        Ok(())
+    }
+}
+
+impl BatchWriter for FileWriter<Box<dyn Write + Send>> {
+    fn write(&mut self, batch: &RecordBatch) -> Result<()> {
+        FileWriter::write(self, batch)?;
+        Ok(())
+    }
+
+    fn finish(self: Box<Self>) -> Result<()> {
+        self.into_inner()?.flush()?;
+        Ok(())
+    }
+}
+
+impl ColumnarFormat {
+    /// Whether the format can store a different dictionary with every
+    /// batch. The Arrow IPC file format can't, so SYMBOL columns are plain
+    /// strings there.
+    fn has_batch_dictionaries(&self) -> bool {
+        !matches!(self, ColumnarFormat::ArrowFile)
+    }
+
+    fn open(&self, out: Box<dyn Write + Send>, schema: &Schema) -> Result<Box<dyn BatchWriter>> {
+        let writer: Box<dyn BatchWriter> = match self {
+            ColumnarFormat::Parquet => {
+                let props = WriterProperties::builder()
+                    .set_compression(Compression::SNAPPY)
+                    .set_max_row_group_size(ROW_GROUP_ROWS)
+                    .build();
+                Box::new(ArrowWriter::try_new(out, Arc::new(schema.clone()), Some(props))?)
+            }
+            ColumnarFormat::ArrowStream => Box::new(StreamWriter::try_new(out, schema)?),
+            ColumnarFormat::ArrowFile => Box::new(FileWriter::try_new(out, schema)?),
+        };
+        Ok(writer)
+    }
+}
+
+/// Writes results in a columnar format, converting rows into record batches
+/// as they stream in so memory use stays bounded.
+pub struct ColumnarSink {
+    format: ColumnarFormat,
+    timestamps: TimestampFormat,
+    out: Option<Box<dyn Write + Send>>,
+    batch: Option<BatchBuilder>,
+    writer: Option<Box<dyn BatchWriter>>,
+}
+
+impl ColumnarSink {
+    pub fn new(out: Box<dyn Write + Send>, timestamps: TimestampFormat, format: ColumnarFormat) -> Self {
+        Self { format, timestamps, out: Some(out), batch: None, writer: None }
+    }
+
+    fn write_batch(&mut self) -> Result<()> {
+        let batch = match self.batch.as_mut() {
+            Some(batch) => batch,
+            None => return Ok(()),
+        };
+        let record_batch = batch.finish()?;
+        if self.writer.is_none() {
+            let out = self.out.take().ok_or_else(|| anyhow!("output already closed"))?;
+            self.writer = Some(self.format.open(out, &record_batch.schema())?);
+        }
+        if let Some(writer) = self.writer.as_mut() {
+            writer.write(&record_batch)?;
+        }
+        Ok(())
+    }
+}
+
+impl ResultSink for ColumnarSink {
+    fn begin(&mut self, columns: &[ColumnMeta]) -> Result<()> {
+        if !columns.is_empty() {
+            self.batch = Some(BatchBuilder::new(columns, self.timestamps.clone(), self.format.has_batch_dictionaries()));
+        }
+        Ok(())
+    }
+
+    fn row(&mut self, values: &[Value]) -> Result<()> {
+        if let Some(batch) = self.batch.as_mut() {
+            batch.push(values);
+            if batch.is_full() {
+                self.write_batch()?;
+            }
+        }
+        Ok(())
+    }
+
+    fn end(&mut self) -> Result<()> {
+        // Always write the last (possibly empty) batch so that even an empty
+        // result produces a valid file carrying the schema.
+        if self.batch.as_ref().is_some_and(|b| !b.is_empty() || self.writer.is_none()) {
+            self.write_batch()?;
+        }
+        if let Some(writer) = self.writer.take() {
+            writer.finish()?;
+        }
+        Ok(())
+    }
+}
@@ -1,41 +1,130 @@
-use crate::output::print_query_results;
+use crate::completer::SQLCompleter;
+use crate::config::{Config, Profile};
+use crate::cli::parse_duration;
+use crate::db::{Backend, Connection, Timeout};
+use crate::http::{self, HttpClient};
+use crate::session::{Session, Sessions};
+use crate::output::{is_broken_pipe, JsonLayout, OutputSettings, TableWidth};
+use crate::pager::PagerMode;
+use crate::value::{decode_row, ColumnMeta, TimestampFormat};
 use anyhow::{anyhow, Result};
-use futures::StreamExt;
+use futures::{Stream, StreamExt};
+use std::future::Future;
+use std::time::{Duration, Instant};
 use tokio_postgres::{Client, types::ToSql};
 
 pub async fn execute_query_command(
-    client: &Client,
+    connection: &Connection,
     query: &str,
-    format: &str,
+    settings: &OutputSettings,
 ) -> Result<()> {
-    let params: &[&(dyn ToSql + Sync)] = &[];
-
-    let stream = client.query_raw(query, params.iter().map(|p| *p)).await?;
-    futures::pin_mut!(stream);
-
-    while let Some(row_result) = stream.next().await {
-        match row_result {
-            Ok(row) => {
-                print_query_results(vec![row], format).await?;
-            }
-            Err(e) => {
-                eprintln!("Error processing row: {}", e);
-                break;
+    let mut deadline = Deadline::new(settings.statement_timeout);
+    let result = match &connection.backend {
+        Backend::Pg(client) => run_pg_query(client, query, settings, &mut deadline).await,
+        Backend::Http(client) => run_http_query(client, query, settings, &mut deadline).await,
+    };
+    match result {
+        // The reader stopped listening; there's nobody left to show the rest to.
+        Err(e) if is_broken_pipe(&e) => Ok(()),
+        Err(e) if e.is::<Timeout>() => {
+            // Stop the server working on a result nobody will read
+            if let Err(e) = connection.cancel().await {
+                eprintln!("Failed to cancel the statement: {}", e);
             }
+            Err(e)
         }
+        other => other,
+    }
+}
+
+/// The statement timeout, charged only for the time spent waiting on the
+/// server. Time spent writing rows, e.g. while a pager waits for the user
+/// or a pipe is slow to read, doesn't count.
+struct Deadline {
+    limit: Option<Duration>,
+    waited: Duration,
+}
+
+impl Deadline {
+    fn new(limit: Option<Duration>) -> Self {
+        Self { limit, waited: Duration::ZERO }
+    }
+
+    /// Waits for the server, failing with a `Timeout` once the waits of the
+    /// statement add up to more than the limit.
+    async fn wait<F: Future>(&mut self, server: F) -> Result<F::Output> {
+        let Some(limit) = self.limit else {
+            return Ok(server.await);
+        };
+        let started = Instant::now();
+        let result = tokio::time::timeout(limit.saturating_sub(self.waited), server).await;
+        self.waited += started.elapsed();
+        result.map_err(|_| Timeout::new("statement", limit).into())
     }
+}
 
+/// Passes each row of a result stream to `row`, stopping at the first
+/// error. The stream is never polled after its end, which tokio-postgres
+/// answers with "connection closed".
+async fn read_rows<S, T, E>(rows: S, deadline: &mut Deadline, mut row: impl FnMut(T) -> Result<()>) -> Result<()>
+where
+    S: Stream<Item = Result<T, E>>,
+    E: std::error::Error + Send + Sync + 'static,
+{
+    let rows = rows.fuse();
+    futures::pin_mut!(rows);
+    while let Some(next) = deadline.wait(rows.next()).await? {
+        row(next?)?;
+    }
     Ok(())
 }
 
+async fn run_pg_query(client: &Client, query: &str, settings: &OutputSettings, deadline: &mut Deadline) -> Result<()> {
+    let params: &[&(dyn ToSql + Sync)] = &[];
+
+    // Preparing first gives us the result columns before any row arrives,
+    // so headers are printed even for empty results.
+    let statement = deadline.wait(client.prepare(query)).await??;
+    let rows = deadline.wait(client.query_raw(&statement, params.iter().copied())).await??;
+
+    let columns: Vec<ColumnMeta> = statement.columns().iter().map(ColumnMeta::from_pg).collect();
+    if columns.is_empty() {
+        // DDL and DML: nothing to print, and no reason to touch the output file
+        return read_rows(rows, deadline, |_| Ok(())).await;
+    }
+    let mut sink = settings.sink()?;
+    sink.begin(&columns)?;
+    read_rows(rows, deadline, |row| sink.row(&decode_row(&row)?)).await?;
+    sink.end()
+}
+
+/// `/exec` rows are decoded as the response arrives and go through the
+/// same sinks as PGWire rows.
+async fn run_http_query(client: &HttpClient, query: &str, settings: &OutputSettings, deadline: &mut Deadline) -> Result<()> {
+    let mut stream = deadline.wait(client.exec_stream(query)).await??;
+    let columns = stream.columns();
+    if columns.is_empty() {
+        return Ok(());
+    }
+    let mut sink = settings.sink()?;
+    sink.begin(&columns)?;
+    while let Some(values) = deadline.wait(stream.next_row()).await?? {
+        sink.row(&http::decode_row(&columns, &values)?)?;
+    }
+    sink.end()
+}
+
 pub async fn handle_meta_command(
-    client: &Client,
+    sessions: &mut Sessions,
     command: &str,
-    completer: &mut crate::completer::SQLCompleter,
-    format: &mut String,
+    completer: &SQLCompleter,
+    settings: &mut OutputSettings,
+    config: &Config,
 ) {
     match command {
         "\\help" => {
+            println!("SQL statements end with ';' and may span lines; Ctrl+C discards an unfinished one.");
+            println!();
             println!("Meta commands:");
             println!("  \\q                 Quit");
             println!("  \\help              Show this help message");
@@ -43,15 +132,28 @@ pub async fn handle_meta_command(
             println!("  \\dwal              List all WAL tables");
             println!("  \\dstorage <table>  Show storage details for a table");
             println!("  \\refresh           Refresh metadata");
-            println!("  \\format [format]   Set output format (table, csv, json, vertical)");
+            println!("  \\format [format]   Show or set the output format");
+            println!("  \\json [options]    Set JSON options (pretty, compact, object, envelope)");
+            println!("  \\pager [mode]      Show or set the pager (on, off, auto)");
+            println!("  \\width [n]         Show or set the table width (auto, off, or columns)");
+            println!("  \\wrap [on|off]     Wrap long table cells instead of truncating them");
+            println!("  \\timezone [zone]   Show or set the time zone for timestamps (IANA name)");
+            println!("  \\tsformat [spec]   Show or set the timestamp format (s, ms, us, ns, iso, or strftime;");
+            println!("                     PGWire timestamps stop at microseconds, so ns needs --transport http)");
+            println!("  \\profiles          List the connection profiles from the config file");
+            println!("  \\profile [name]    Show the current profile or reconnect using another one");
+            println!("  \\connect <target>  Open another connection to a profile or URL (… as <name>)");
+            println!("  \\conns             List the open connections");
+            println!("  \\use <name>        Switch to another open connection");
+            println!("  \\timeout [t]       Show or set the statement timeout (e.g. 30s, 500ms, off)");
         }
         "\\dt" => {
-            if let Err(e) = execute_query_command(client, "SELECT * FROM tables()", format).await {
+            if let Err(e) = execute_query_command(sessions.connection(), "SELECT * FROM tables()", settings).await {
                 eprintln!("Error executing \\dt: {}", e);
             }
         }
         "\\dwal" => {
-            if let Err(e) = execute_query_command(client, "SELECT * FROM wal_tables()", format).await {
+            if let Err(e) = execute_query_command(sessions.connection(), "SELECT * FROM wal_tables()", settings).await {
                 eprintln!("Error executing \\dwal: {}", e);
             }
         }
@@ -61,14 +163,14 @@ pub async fn handle_meta_command(
                 eprintln!("Usage: \\dstorage <table>");
             } else {
                 let query = format!("SELECT * FROM table_storage('{}')", table);
-                if let Err(e) = execute_query_command(client, &query, format).await {
+                if let Err(e) = execute_query_command(sessions.connection(), &query, settings).await {
                     eprintln!("Error executing \\dstorage: {}", e);
                 }
             }
         }
         "\\refresh" => {
             println!("Refreshing metadata...");
-            if let Err(e) = completer.update_tables(client).await {
+            if let Err(e) = completer.update_metadata(sessions.connection()).await {
                 eprintln!("Failed to refresh metadata: {}", e);
             } else {
                 println!("Metadata refreshed.");
@@ -77,18 +179,252 @@ pub async fn handle_meta_command(
         cmd if cmd.starts_with("\\format") => {
             let args = cmd.trim_start_matches("\\format").trim();
             if args.is_empty() {
-                println!("Current format: {}", format);
-                println!("Available formats: table, csv, json, vertical");
+                println!("Current format: {}", settings.format);
+                println!("Available formats: table, csv, json, ndjson, vertical, record, markdown, html, latex, parquet, arrow, arrow-file");
             } else {
-                *format = args.to_string();
-                println!("Output format set to '{}'", format);
+                settings.format = args.to_string();
+                println!("Output format set to '{}'", settings.format);
+            }
+        }
+        cmd if cmd.starts_with("\\json") => {
+            let args = cmd.trim_start_matches("\\json").trim();
+            for arg in args.split_whitespace() {
+                match arg.to_lowercase().as_str() {
+                    "pretty" => settings.json.pretty = true,
+                    "compact" => settings.json.pretty = false,
+                    other => match JsonLayout::from_str(other) {
+                        Some(layout) => settings.json.layout = layout,
+                        None => {
+                            eprintln!("Unknown JSON option '{}'. Use pretty, compact, object or envelope.", other);
+                            return;
+                        }
+                    },
+                }
+            }
+            println!(
+                "JSON output: {}, {} layout",
+                if settings.json.pretty { "pretty" } else { "compact" },
+                settings.json.layout
+            );
+        }
+        cmd if cmd.starts_with("\\pager") => {
+            let args = cmd.trim_start_matches("\\pager").trim();
+            if args.is_empty() {
+                println!("Pager: {}", settings.pager);
+            } else {
+                match PagerMode::from_str(args) {
+                    Some(mode) => {
+                        settings.pager = mode;
+                        println!("Pager set to '{}'", settings.pager);
+                    }
+                    None => eprintln!("Usage: \\pager on|off|auto"),
+                }
+            }
+        }
+        cmd if cmd.starts_with("\\width") => {
+            let args = cmd.trim_start_matches("\\width").trim();
+            if args.is_empty() {
+                println!("Table width: {}", settings.width);
+            } else {
+                match TableWidth::from_str(args) {
+                    Some(width) => {
+                        settings.width = width;
+                        println!("Table width set to '{}'", settings.width);
+                    }
+                    None => eprintln!("Usage: \\width auto|off|<columns>"),
+                }
+            }
+        }
+        cmd if cmd.starts_with("\\wrap") => {
+            match cmd.trim_start_matches("\\wrap").trim() {
+                "" => {}
+                "on" => settings.wrap = true,
+                "off" => settings.wrap = false,
+                _ => {
+                    eprintln!("Usage: \\wrap on|off");
+                    return;
+                }
+            }
+            println!("Cell wrapping: {}", if settings.wrap { "on" } else { "off" });
+        }
+        cmd if cmd.starts_with("\\timezone") => {
+            let args = cmd.trim_start_matches("\\timezone").trim();
+            if !args.is_empty() {
+                match TimestampFormat::parse_timezone(args) {
+                    Ok(timezone) => settings.timestamps.timezone = timezone,
+                    Err(e) => {
+                        eprintln!("{}", e);
+                        return;
+                    }
+                }
+            }
+            println!("Time zone: {}", settings.timestamps.timezone);
+        }
+        cmd if cmd.starts_with("\\tsformat") => {
+            let args = cmd.trim_start_matches("\\tsformat").trim();
+            if !args.is_empty() {
+                if let Err(e) = settings.timestamps.set_format(args) {
+                    eprintln!("{}", e);
+                    return;
+                }
+            }
+            println!("Timestamp format: {}", settings.timestamps);
+        }
+        cmd if cmd.starts_with("\\timeout") => {
+            match cmd.trim_start_matches("\\timeout").trim() {
+                "" => {}
+                "off" | "0" => settings.statement_timeout = None,
+                arg => match parse_duration(arg) {
+                    Ok(limit) => settings.statement_timeout = Some(limit),
+                    Err(e) => {
+                        eprintln!("{}", e);
+                        return;
+                    }
+                },
+            }
+            match settings.statement_timeout {
+                Some(limit) => println!("Statement timeout: {:?}", limit),
+                None => println!("Statement timeout: off"),
+            }
+        }
+        "\\profiles" => {
+            if config.profiles.is_empty() {
+                println!("No profiles configured in {}", Config::default_path().display());
+            }
+            let current = sessions.active().profile.as_deref();
+            for (name, profile) in &config.profiles {
+                let params = profile.connection_params();
+                let marker = if current == Some(name.as_str()) { '*' } else { ' ' };
+                let tls = if params.tls.enabled { " (tls)" } else { "" };
+                println!("{} {:<16} {}@{}:{}{}", marker, name, params.user, params.host, params.port, tls);
+            }
+        }
+        cmd if cmd.starts_with("\\profile") => {
+            let name = cmd.trim_start_matches("\\profile").trim();
+            if name.is_empty() {
+                match &sessions.active().profile {
+                    Some(current) => println!("Current profile: {}", current),
+                    None => println!("No profile in use"),
+                }
+                return;
+            }
+            if let Err(e) = switch_profile(sessions, config, name, completer, settings).await {
+                eprintln!("Failed to connect with profile '{}': {:#}", name, e);
+            }
+        }
+        "\\conns" => {
+            for (active, session) in sessions.iter() {
+                let marker = if active { '*' } else { ' ' };
+                let params = &session.connection.params;
+                let profile = session.profile.as_deref().map(|p| format!(" (profile {})", p)).unwrap_or_default();
+                let state = if session.connection.is_closed() { " [closed]" } else { "" };
+                println!("{} {:<16} {}@{}:{}{}{}", marker, session.name, params.user, params.host, params.port, profile, state);
+            }
+        }
+        cmd if cmd.starts_with("\\connect") => {
+            let args: Vec<&str> = cmd.trim_start_matches("\\connect").split_whitespace().collect();
+            let (target, alias) = match args.as_slice() {
+                [target] => (*target, None),
+                [target, "as", alias] => (*target, Some(*alias)),
+                _ => {
+                    eprintln!("Usage: \\connect <profile|url> [as <name>]");
+                    return;
+                }
+            };
+            if let Err(e) = open_session(sessions, config, target, alias, completer).await {
+                eprintln!("Failed to connect to '{}': {:#}", target, e);
+            }
+        }
+        cmd if cmd.starts_with("\\use") => {
+            let name = cmd.trim_start_matches("\\use").trim();
+            if name.is_empty() {
+                println!("Using '{}' ({})", sessions.active().name, sessions.connection().address());
+                return;
+            }
+            if let Err(e) = sessions.switch(name) {
+                eprintln!("{}", e);
+                return;
+            }
+            println!("Using '{}' ({})", name, sessions.connection().address());
+            if let Err(e) = completer.update_metadata(sessions.connection()).await {
+                eprintln!("Failed to fetch completion metadata: {}", e);
             }
         }
         _ => println!("Unknown meta command: {}", command),
     }
 }
 
-pub async fn execute_script(client: &Client, source: &str, format: &str) -> Result<()> {
+/// Replaces the active connection with one opened from a profile.
+async fn switch_profile(
+    sessions: &mut Sessions,
+    config: &Config,
+    name: &str,
+    completer: &SQLCompleter,
+    settings: &mut OutputSettings,
+) -> Result<()> {
+    let profile = config.profile(name)?;
+    let mut params = profile.resolve(false)?;
+    params.connect_timeout = sessions.connection().params.connect_timeout;
+    let connection = Connection::open(params).await?;
+    // Rename the session too, so the prompt and \conns show where queries go
+    let session_name = if sessions.active().name == name {
+        name.to_string()
+    } else {
+        sessions.unique_name(name)
+    };
+    let session = sessions.active_mut();
+    session.connection = connection;
+    session.profile = Some(name.to_string());
+    session.name = session_name;
+    if let Some(format) = &profile.format {
+        settings.format = format.clone();
+    }
+    println!(
+        "Connected to QuestDB at {} (profile '{}') as '{}'.",
+        sessions.connection().address(),
+        name,
+        sessions.active().name
+    );
+    if let Err(e) = completer.update_metadata(sessions.connection()).await {
+        eprintln!("Failed to fetch completion metadata: {}", e);
+    }
+    Ok(())
+}
+
+/// Opens a further connection to a profile or URL and makes it active.
+async fn open_session(
+    sessions: &mut Sessions,
+    config: &Config,
+    target: &str,
+    alias: Option<&str>,
+    completer: &SQLCompleter,
+) -> Result<()> {
+    let (profile, profile_name) = if config.profiles.contains_key(target) {
+        (config.profile(target)?.clone(), Some(target.to_string()))
+    } else if target.contains("://") || target.contains('=') {
+        (Profile::from_url(target)?, None)
+    } else {
+        return Err(config.profile(target).unwrap_err());
+    };
+    let mut params = profile.resolve(false)?;
+    params.connect_timeout = sessions.connection().params.connect_timeout;
+    let name = match alias {
+        Some(alias) if sessions.iter().any(|(_, s)| s.name == alias) => {
+            return Err(anyhow!("a connection named '{}' is already open", alias));
+        }
+        Some(alias) => alias.to_string(),
+        None => sessions.unique_name(profile_name.as_deref().unwrap_or(&params.host)),
+    };
+    let connection = Connection::open(params).await?;
+    println!("Connected to QuestDB at {} as '{}'.", connection.address(), name);
+    sessions.add(Session { name, profile: profile_name, connection });
+    if let Err(e) = completer.update_metadata(sessions.connection()).await {
+        eprintln!("Failed to fetch completion metadata: {}", e);
+    }
+    Ok(())
+}
+
+pub async fn execute_script(connection: &Connection, source: &str, settings: &OutputSettings) -> Result<()> {
     let content = if source.starts_with("http://") || source.starts_with("https://") {
         let response = reqwest::Client::new().get(source).send().await?;
         response.text().await?
@@ -103,10 +439,84 @@ pub async fn execute_script(client: &Client, source: &str, format: &str) -> Resu
     for statement in statements {
         let query = statement.to_string();
         println!("Executing: {}", query);
-        if let Err(e) = execute_query_command(client, &query, format).await {
-            eprintln!("Error executing query in script: {}", e);
+        match execute_query_command(connection, &query, settings).await {
+            // a hung server would hold up every statement after this one too
+            Err(e) if e.is::<Timeout>() => return Err(e),
+            Err(e) => eprintln!("Error executing query in script: {}", e),
+            Ok(()) => {}
         }
     }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io;
+    use std::task::Poll;
+
+    /// A result stream that, like tokio-postgres, fails when it is polled
+    /// again after its end.
+    fn result_stream(rows: Vec<Result<i32, io::Error>>) -> impl Stream<Item = Result<i32, io::Error>> {
+        let mut rows = rows.into_iter();
+        let mut ended = false;
+        futures::stream::poll_fn(move |_| {
+            Poll::Ready(match rows.next() {
+                Some(row) => Some(row),
+                None if !ended => {
+                    ended = true;
+                    None
+                }
+                None => Some(Err(io::Error::other("connection closed"))),
+            })
+        })
+    }
+
+    #[tokio::test]
+    async fn read_rows_handles_empty_results() {
+        let mut deadline = Deadline::new(Some(Duration::from_secs(5)));
+        let mut seen = 0;
+        read_rows(result_stream(vec![]), &mut deadline, |_| {
+            seen += 1;
+            Ok(())
+        })
+        .await
+        .unwrap();
+        assert_eq!(seen, 0);
+    }
+
+    #[tokio::test]
+    async fn read_rows_fails_on_an_error_mid_stream() {
+        let mut deadline = Deadline::new(None);
+        let mut seen = Vec::new();
+        let stream = result_stream(vec![Ok(1), Err(io::Error::other("server went away")), Ok(3)]);
+        let result = read_rows(stream, &mut deadline, |row| {
+            seen.push(row);
+            Ok(())
+        })
+        .await;
+        assert_eq!(result.unwrap_err().to_string(), "server went away");
+        assert_eq!(seen, vec![1]);
+    }
+
+    #[tokio::test]
+    async fn deadline_stops_a_stalled_stream() {
+        let mut deadline = Deadline::new(Some(Duration::from_millis(50)));
+        let stalled = result_stream(vec![Ok(1)]).chain(futures::stream::pending());
+        let result = read_rows(stalled, &mut deadline, |_| Ok(())).await;
+        assert!(result.unwrap_err().is::<Timeout>());
+    }
+
+    #[tokio::test]
+    async fn deadline_ignores_time_spent_writing_rows() {
+        let mut deadline = Deadline::new(Some(Duration::from_millis(50)));
+        let stream = result_stream(vec![Ok(1), Ok(2), Ok(3)]);
+        read_rows(stream, &mut deadline, |_| {
+            std::thread::sleep(Duration::from_millis(30));
+            Ok(())
+        })
+        .await
+        .unwrap();
+    }
+}
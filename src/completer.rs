@@ -1,55 +1,304 @@
 // src/completer.rs
 
+use crate::db::Connection;
+use crate::highlight::Styles;
+use futures::stream::{self, StreamExt};
 use rustyline::completion::{Completer, Pair};
 use rustyline::highlight::Highlighter;
-use rustyline::hint::Hinter;
+use rustyline::hint::{Hint, Hinter};
 use rustyline::validate::{Validator, ValidationResult, ValidationContext};
 use rustyline::{Context};
+use crate::sql::{self, Token, TokenKind};
 use rustyline::error::ReadlineError;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::{Arc, RwLock, RwLockReadGuard};
+use std::time::Duration;
+use tokio::sync::watch;
+use tokio::time::{Instant, MissedTickBehavior};
 
+/// Keyword sequences completed as a whole.
+const PHRASES: &[&str] = &[
+    "ALIGN TO CALENDAR",
+    "ASOF JOIN",
+    "BYPASS WAL",
+    "CROSS JOIN",
+    "DEDUP UPSERT KEYS",
+    "GROUP BY",
+    "LATEST ON",
+    "LEFT JOIN",
+    "LT JOIN",
+    "ORDER BY",
+    "PARTITION BY",
+    "SAMPLE BY",
+    "SPLICE JOIN",
+];
+
+/// Keywords followed by a table name.
+const TABLE_KEYWORDS: &[&str] = &["FROM", "JOIN", "INTO", "UPDATE", "TABLE"];
+
+/// Keywords followed by a column or an expression.
+const COLUMN_KEYWORDS: &[&str] = &[
+    "SELECT", "WHERE", "AND", "OR", "NOT", "ON", "BY", "HAVING", "SET", "DISTINCT", "CASE", "WHEN", "THEN", "ELSE",
+];
+
+/// The completer is cloned into the line editor; clones share the
+/// metadata, so a refresh through any of them reaches the editor.
 #[derive(Clone)]
 pub struct SQLCompleter {
-    pub keywords: Vec<String>,
     pub meta_commands: Vec<String>,
+    metadata: Arc<RwLock<Metadata>>,
+    /// Syntax highlighting; None leaves input uncoloured.
+    pub styles: Option<Styles>,
+}
+
+/// What the server has, as far as completion and hints are concerned.
+#[derive(Default)]
+pub struct Metadata {
     pub tables: Vec<String>,
+    /// Columns of each table.
+    pub columns: BTreeMap<String, Vec<ColumnInfo>>,
+    /// Signatures of the built-in functions, by name.
+    pub functions: BTreeMap<String, Vec<String>>,
+}
+
+/// A table column, with the QuestDB type `table_columns()` reports for it.
+#[derive(Clone)]
+pub struct ColumnInfo {
+    pub name: String,
+    pub column_type: String,
+    /// Whether it is the table's designated timestamp.
+    pub designated: bool,
+}
+
+/// A hint that only informs: unlike a `String` hint, the right arrow
+/// doesn't insert it into the line.
+pub struct InfoHint(String);
+
+impl Hint for InfoHint {
+    fn display(&self) -> &str {
+        &self.0
+    }
+
+    fn completion(&self) -> Option<&str> {
+        None
+    }
+}
+
+/// What the word at the cursor is expected to be.
+enum Expect {
+    Table,
+    /// A column, a function or a keyword, as in an expression.
+    Column,
+    Keyword,
 }
 
 impl SQLCompleter {
     pub fn new() -> Self {
         Self {
-            keywords: vec![
-                "SELECT".to_string(),
-                "FROM".to_string(),
-                "WHERE".to_string(),
-                "INSERT".to_string(),
-                "UPDATE".to_string(),
-                "DELETE".to_string(),
-                "LIMIT".to_string(),
-                "JOIN".to_string(),
-                "ON".to_string(),
-                // Add more SQL keywords as needed
-            ],
             meta_commands: vec![
                 "\\help".to_string(),
                 "\\q".to_string(),
                 "\\format".to_string(),
+                "\\json".to_string(),
+                "\\pager".to_string(),
+                "\\width".to_string(),
+                "\\wrap".to_string(),
+                "\\timezone".to_string(),
+                "\\tsformat".to_string(),
+                "\\profile".to_string(),
+                "\\profiles".to_string(),
+                "\\connect".to_string(),
+                "\\conns".to_string(),
+                "\\use".to_string(),
+                "\\timeout".to_string(),
                 "\\dt".to_string(),
                 "\\dwal".to_string(),
                 "\\dstorage".to_string(),
                 "\\refresh".to_string(),
                 // Add more meta commands as needed
             ],
-            tables: vec![], // Will be populated dynamically
+            metadata: Arc::default(), // Will be populated dynamically
+            styles: None,
         }
     }
 
-    pub async fn update_tables(&mut self, client: &tokio_postgres::Client) -> Result<(), tokio_postgres::Error> {
-        let rows = client.query("SELECT table_name FROM information_schema.tables WHERE table_schema = 'public';", &[]).await?;
-        self.tables = rows.iter()
-            .filter_map(|row| row.get::<_, Option<String>>(0))
-            .collect();
+    /// Fetches the metadata anew. The previous one stays in use until the
+    /// new one is complete.
+    pub async fn update_metadata(&self, connection: &Connection) -> anyhow::Result<()> {
+        let metadata = Metadata::fetch(connection).await?;
+        *self.metadata.write().unwrap() = metadata;
         Ok(())
     }
+
+    /// Refreshes the metadata in the background every `interval`, from
+    /// the connection `active` holds, until its sender is dropped.
+    pub fn refresh_periodically(&self, mut active: watch::Receiver<Connection>, interval: Duration) {
+        let completer = self.clone();
+        tokio::spawn(async move {
+            let mut ticks = tokio::time::interval_at(Instant::now() + interval, interval);
+            ticks.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            loop {
+                ticks.tick().await;
+                if active.has_changed().is_err() {
+                    break;
+                }
+                let connection = active.borrow_and_update().clone();
+                // errors would interrupt typing; the next tick tries again
+                let _ = completer.update_metadata(&connection).await;
+            }
+        });
+    }
+
+    fn metadata(&self) -> RwLockReadGuard<'_, Metadata> {
+        self.metadata.read().unwrap()
+    }
+}
+
+impl Metadata {
+    /// Fetches the tables, columns and functions completion and hints
+    /// draw on.
+    async fn fetch(connection: &Connection) -> anyhow::Result<Metadata> {
+        let tables = connection
+            .query_strings("SELECT table_name FROM information_schema.tables WHERE table_schema = 'public';")
+            .await?;
+        let columns = Self::fetch_columns(connection, &tables).await;
+        let mut functions: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        // without functions, completion still has the tables and columns
+        let rows = connection.query_text("SELECT name, signature_translated FROM functions();").await;
+        for row in rows.unwrap_or_default() {
+            if let [Some(name), signature] = row.as_slice() {
+                functions.entry(name.clone()).or_default().extend(signature.clone());
+            }
+        }
+        Ok(Metadata { tables, columns, functions })
+    }
+
+    /// Fetches the columns of every table with `table_columns()`, the only
+    /// source of QuestDB's own type names such as SYMBOL. The queries run
+    /// side by side, so that large servers don't take a round trip per table;
+    /// a table whose query fails is left without columns.
+    async fn fetch_columns(connection: &Connection, tables: &[String]) -> BTreeMap<String, Vec<ColumnInfo>> {
+        const CONCURRENT_QUERIES: usize = 16;
+        // collected first: a lazily mapped iterator keeps the future from being Send
+        let queries: Vec<_> = tables.iter().map(|table| Self::fetch_table_columns(connection, table)).collect();
+        let fetched: Vec<_> = stream::iter(queries)
+            .buffer_unordered(CONCURRENT_QUERIES)
+            .collect()
+            .await;
+        fetched.into_iter().flatten().collect()
+    }
+
+    async fn fetch_table_columns(connection: &Connection, table: &str) -> Option<(String, Vec<ColumnInfo>)> {
+        let query = format!(
+            "SELECT \"column\", type, designated FROM table_columns('{}');",
+            table.replace('\'', "''")
+        );
+        let columns = connection
+            .query_text(&query)
+            .await
+            .ok()?
+            .into_iter()
+            .filter_map(|row| match <[_; 3]>::try_from(row) {
+                Ok([Some(name), column_type, designated]) => Some(ColumnInfo {
+                    name,
+                    column_type: column_type.unwrap_or_default(),
+                    designated: matches!(designated.as_deref(), Some("t" | "true")),
+                }),
+                _ => None,
+            })
+            .collect();
+        Some((table.to_string(), columns))
+    }
+
+    pub fn is_table(&self, name: &str) -> bool {
+        self.tables.iter().any(|t| t.eq_ignore_ascii_case(name))
+    }
+
+    pub fn is_column(&self, name: &str) -> bool {
+        self.columns.values().flatten().any(|c| c.name.eq_ignore_ascii_case(name))
+    }
+
+    fn table_columns(&self, table: &str) -> &[ColumnInfo] {
+        self.columns
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(table))
+            .map_or(&[], |(_, columns)| columns)
+    }
+
+    /// Columns of the tables the statement reads, or of all tables if it
+    /// names none yet.
+    fn referenced_columns(&self, line: &str) -> Vec<&ColumnInfo> {
+        let referenced: Vec<&str> = table_refs(line)
+            .into_iter()
+            .map(|(table, _)| table)
+            .filter(|table| self.is_table(table))
+            .collect();
+        if referenced.is_empty() {
+            self.columns.values().flatten().collect()
+        } else {
+            referenced.iter().flat_map(|table| self.table_columns(table)).collect()
+        }
+    }
+
+    /// Candidates for a word in an expression: columns, functions and
+    /// keywords.
+    fn expression_candidates(&self, line: &str, word: &str) -> Vec<Pair> {
+        let columns: BTreeSet<&String> = self.referenced_columns(line).into_iter().map(|c| &c.name).collect();
+        let mut candidates = names(columns, word);
+        candidates.extend(self.functions.keys().filter(|f| has_prefix(f, word)).map(|f| Pair {
+            display: f.clone(),
+            replacement: format!("{}(", f),
+        }));
+        candidates.extend(keywords(word));
+        candidates
+    }
+
+    /// The type of the column named by the word before the cursor.
+    fn column_hint(&self, line: &str, tokens: &[Token]) -> Option<String> {
+        let (word, earlier) = match tokens.split_last() {
+            Some((last, earlier)) if matches!(last.kind, TokenKind::Word | TokenKind::QuotedIdent) => {
+                (unquote(&line[last.start..last.end]), earlier)
+            }
+            _ => return None,
+        };
+        let column = match earlier {
+            [.., qualifier, dot] if dot.kind == TokenKind::Punct && &line[dot.start..dot.end] == "." => {
+                let table = qualified_table(line, unquote(&line[qualifier.start..qualifier.end]));
+                self.table_columns(table).iter().find(|c| c.name.eq_ignore_ascii_case(word))
+            }
+            _ => self.referenced_columns(line).into_iter().find(|c| c.name.eq_ignore_ascii_case(word)),
+        }?;
+        let designated = if column.designated { ", designated timestamp" } else { "" };
+        Some(format!("  {}{}", column.column_type, designated))
+    }
+
+    /// The signatures of the function whose argument list the cursor is in.
+    fn signature_hint(&self, line: &str, tokens: &[Token]) -> Option<String> {
+        const SHOWN: usize = 3;
+        let mut open = Vec::new();
+        for (i, token) in tokens.iter().enumerate().filter(|(_, t)| t.kind == TokenKind::Punct) {
+            match &line[token.start..token.end] {
+                "(" => open.push(i),
+                ")" => {
+                    open.pop();
+                }
+                _ => {}
+            }
+        }
+        let name = tokens[..*open.last()?]
+            .iter()
+            .rev()
+            .find(|t| t.kind != TokenKind::Whitespace)
+            .filter(|t| t.kind == TokenKind::Word)?;
+        let name = &line[name.start..name.end];
+        let (_, signatures) = self.functions.iter().find(|(f, _)| f.eq_ignore_ascii_case(name))?;
+        let mut hint = format!("  {}", signatures.iter().take(SHOWN).cloned().collect::<Vec<_>>().join(" | "));
+        if signatures.len() > SHOWN {
+            hint.push_str(&format!(" | {} more", signatures.len() - SHOWN));
+        }
+        Some(hint)
+    }
 }
 
 impl Completer for SQLCompleter {
@@ -61,31 +310,318 @@ impl Completer for SQLCompleter {
         pos: usize,
         _ctx: &Context<'_>,
     ) -> Result<(usize, Vec<Pair>), ReadlineError> {
-        let start = line[..pos].rfind(|c: char| c.is_whitespace()).map_or(0, |idx| idx + 1);
-        let word = &line[start..pos].to_uppercase();
-        let candidates = self.keywords.iter()
-            .filter(|kw| kw.starts_with(word))
-            .chain(self.meta_commands.iter().filter(|cmd| cmd.starts_with(&line[start..pos])))
-            .chain(self.tables.iter().filter(|tbl| tbl.to_uppercase().starts_with(word)))
-            .map(|kw| Pair {
-                display: kw.clone(),
-                replacement: kw.clone(),
-            })
-            .collect();
+        let metadata = self.metadata();
+        let before = &line[..pos];
+        let command = before.trim_start();
+        if command.starts_with('\\') {
+            // the command itself, then a table for those taking one
+            return Ok(match command.rfind(char::is_whitespace) {
+                None => {
+                    let candidates = self.meta_commands.iter().filter(|cmd| cmd.starts_with(command));
+                    (pos - command.len(), names(candidates, ""))
+                }
+                Some(i) => (pos - command.len() + i + 1, names(&metadata.tables, &command[i + 1..])),
+            });
+        }
+
+        let tokens = sql::tokenize(before);
+        let (start, earlier) = match tokens.split_last() {
+            Some((last, earlier)) if last.kind == TokenKind::Word => (last.start, earlier),
+            _ => (pos, &tokens[..]),
+        };
+        let word = &before[start..];
+
+        // `alias.` or `table.` is followed by that table's columns
+        if let [.., qualifier, dot] = earlier {
+            if dot.kind == TokenKind::Punct && &before[dot.start..dot.end] == "." {
+                let table = qualified_table(line, unquote(&before[qualifier.start..qualifier.end]));
+                let columns = metadata.table_columns(table).iter().map(|c| &c.name);
+                return Ok((start, names(columns, word)));
+            }
+        }
+
+        let candidates = match expect(before, earlier) {
+            Expect::Table => names(&metadata.tables, word),
+            Expect::Column => metadata.expression_candidates(line, word),
+            Expect::Keyword => keywords(word),
+        };
         Ok((start, candidates))
     }
 }
 
+/// What the tokens before the cursor call for next.
+fn expect(text: &str, tokens: &[Token]) -> Expect {
+    let mut significant = tokens
+        .iter()
+        .rev()
+        .filter(|t| !matches!(t.kind, TokenKind::Whitespace | TokenKind::Comment));
+    let last = match significant.next() {
+        Some(last) => last,
+        None => return Expect::Keyword,
+    };
+    let last_text = &text[last.start..last.end];
+    match last.kind {
+        TokenKind::Word if is_one_of(last_text, TABLE_KEYWORDS) => Expect::Table,
+        TokenKind::Word if is_one_of(last_text, COLUMN_KEYWORDS) => Expect::Column,
+        // after a closed call or sub-query comes the rest of the clause
+        TokenKind::Punct if last_text != ")" => {
+            // a list or an expression goes on in the clause it is part of
+            let clause = significant.find(|t| {
+                let word = &text[t.start..t.end];
+                t.kind == TokenKind::Word && (is_one_of(word, TABLE_KEYWORDS) || is_one_of(word, COLUMN_KEYWORDS))
+            });
+            match clause {
+                Some(t) if is_one_of(&text[t.start..t.end], TABLE_KEYWORDS) => {
+                    if last_text == "," {
+                        Expect::Table
+                    } else {
+                        Expect::Keyword
+                    }
+                }
+                Some(_) => Expect::Column,
+                None => Expect::Keyword,
+            }
+        }
+        _ => Expect::Keyword,
+    }
+}
+
+/// The table a column qualifier stands for: the table it is an alias of,
+/// or else a table name itself.
+fn qualified_table<'a>(line: &'a str, qualifier: &'a str) -> &'a str {
+    table_refs(line)
+        .into_iter()
+        .find(|(_, alias)| alias.is_some_and(|a| a.eq_ignore_ascii_case(qualifier)))
+        .map_or(qualifier, |(table, _)| table)
+}
+
+/// The tables named after FROM or JOIN, with their aliases.
+fn table_refs(line: &str) -> Vec<(&str, Option<&str>)> {
+    let tokens: Vec<Token> = sql::tokenize(line)
+        .into_iter()
+        .filter(|t| !matches!(t.kind, TokenKind::Whitespace | TokenKind::Comment))
+        .collect();
+    let text = |t: &Token| &line[t.start..t.end];
+    let name = |t: Option<&Token>| {
+        t.filter(|t| t.kind == TokenKind::QuotedIdent || (t.kind == TokenKind::Word && !sql::is_keyword(text(t))))
+            .map(|t| unquote(text(t)))
+    };
+    let mut refs = Vec::new();
+    let mut i = 0;
+    while i < tokens.len() {
+        i += 1;
+        if !is_one_of(text(&tokens[i - 1]), &["FROM", "JOIN"]) {
+            continue;
+        }
+        // FROM a [AS] x, b [AS] y
+        while let Some(table) = name(tokens.get(i)) {
+            i += 1;
+            if tokens.get(i).is_some_and(|t| text(t).eq_ignore_ascii_case("AS")) {
+                i += 1;
+            }
+            let alias = name(tokens.get(i));
+            if alias.is_some() {
+                i += 1;
+            }
+            refs.push((table, alias));
+            if tokens.get(i).is_some_and(|t| text(t) == ",") {
+                i += 1;
+            } else {
+                break;
+            }
+        }
+    }
+    refs
+}
+
+/// Keywords and keyword phrases starting with `word`, in its case.
+fn keywords(word: &str) -> Vec<Pair> {
+    let lower = word.chars().any(char::is_lowercase);
+    PHRASES
+        .iter()
+        .chain(sql::KEYWORDS)
+        .filter(|k| has_prefix(k, word))
+        .map(|k| {
+            let keyword = if lower { k.to_lowercase() } else { k.to_string() };
+            Pair { display: keyword.clone(), replacement: keyword }
+        })
+        .collect()
+}
+
+/// Candidates for the names starting with `word`.
+fn names<'a>(names: impl IntoIterator<Item = &'a String>, word: &str) -> Vec<Pair> {
+    names
+        .into_iter()
+        .filter(|name| has_prefix(name, word))
+        .map(|name| Pair { display: name.clone(), replacement: name.clone() })
+        .collect()
+}
+
+fn has_prefix(name: &str, prefix: &str) -> bool {
+    name.get(..prefix.len()).is_some_and(|start| start.eq_ignore_ascii_case(prefix))
+}
+
+fn is_one_of(word: &str, keywords: &[&str]) -> bool {
+    keywords.iter().any(|k| k.eq_ignore_ascii_case(word))
+}
+
+fn unquote(name: &str) -> &str {
+    name.trim_matches('"')
+}
+
+// Hints describe what is under the cursor at the end of the line: the
+// column just typed, or else the function call it is in.
 impl Hinter for SQLCompleter {
-    type Hint = String;
+    type Hint = InfoHint;
+
+    fn hint(&self, line: &str, pos: usize, _ctx: &Context<'_>) -> Option<InfoHint> {
+        if pos < line.len() || line.trim_start().starts_with('\\') {
+            return None;
+        }
+        let metadata = self.metadata();
+        let tokens = sql::tokenize(line);
+        metadata
+            .column_hint(line, &tokens)
+            .or_else(|| metadata.signature_hint(line, &tokens))
+            .map(InfoHint)
+    }
 }
 
+// Enter submits the input once it is complete; until then it starts a new
+// line of the same input.
 impl Validator for SQLCompleter {
-    fn validate(&self, _ctx: &mut ValidationContext<'_>) -> Result<ValidationResult, ReadlineError> {
-        Ok(ValidationResult::Valid(None))
+    fn validate(&self, ctx: &mut ValidationContext<'_>) -> Result<ValidationResult, ReadlineError> {
+        Ok(if is_complete(ctx.input()) { ValidationResult::Valid(None) } else { ValidationResult::Incomplete })
     }
 }
 
-impl Highlighter for SQLCompleter {}
+/// Whether input is ready to run: a meta command, which takes a single
+/// line, or SQL whose last statement is ended by a `;`.
+fn is_complete(input: &str) -> bool {
+    input.trim_start().starts_with('\\') || sql::is_blank(sql::split_statements(input).1)
+}
+
+impl Highlighter for SQLCompleter {
+    fn highlight<'l>(&self, line: &'l str, pos: usize) -> Cow<'l, str> {
+        match &self.styles {
+            Some(styles) if !line.trim_start().starts_with('\\') => {
+                let metadata = self.metadata();
+                Cow::Owned(styles.highlight(line, pos, |name| metadata.is_table(name), |name| metadata.is_column(name)))
+            }
+            _ => Cow::Borrowed(line),
+        }
+    }
+
+    fn highlight_hint<'h>(&self, hint: &'h str) -> Cow<'h, str> {
+        match &self.styles {
+            Some(styles) => Cow::Owned(styles.hint(hint)),
+            None => Cow::Borrowed(hint),
+        }
+    }
+
+    // The bracket match follows the cursor, so every edit redraws the line
+    fn highlight_char(&self, _line: &str, _pos: usize) -> bool {
+        self.styles.is_some()
+    }
+}
 
 impl rustyline::Helper for SQLCompleter {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metadata() -> Metadata {
+        let column = |name: &str, column_type: &str, designated| ColumnInfo {
+            name: name.to_string(),
+            column_type: column_type.to_string(),
+            designated,
+        };
+        Metadata {
+            tables: vec!["trades".to_string(), "quotes".to_string()],
+            columns: BTreeMap::from([
+                (
+                    "trades".to_string(),
+                    vec![column("ts", "TIMESTAMP", true), column("symbol", "SYMBOL", false), column("price", "DOUBLE", false)],
+                ),
+                ("quotes".to_string(), vec![column("ts", "TIMESTAMP", true), column("bid", "DOUBLE", false)]),
+            ]),
+            functions: BTreeMap::from([
+                ("avg".to_string(), vec!["avg(D)".to_string(), "avg(L)".to_string()]),
+                ("now".to_string(), vec!["now()".to_string()]),
+            ]),
+        }
+    }
+
+    fn expected(text: &str) -> Expect {
+        expect(text, &sql::tokenize(text))
+    }
+
+    fn displays(pairs: Vec<Pair>) -> Vec<String> {
+        pairs.into_iter().map(|p| p.display).collect()
+    }
+
+    #[test]
+    fn expect_follows_the_clause() {
+        assert!(matches!(expected(""), Expect::Keyword));
+        assert!(matches!(expected("SELECT * FROM "), Expect::Table));
+        assert!(matches!(expected("select * from a, "), Expect::Table));
+        assert!(matches!(expected("SELECT "), Expect::Column));
+        assert!(matches!(expected("SELECT a, "), Expect::Column));
+        assert!(matches!(expected("SELECT * FROM t WHERE a = "), Expect::Column));
+        assert!(matches!(expected("SELECT * FROM t /* x */ WHERE "), Expect::Column));
+        assert!(matches!(expected("SELECT * FROM t "), Expect::Keyword));
+        assert!(matches!(expected("SELECT count() "), Expect::Keyword));
+    }
+
+    #[test]
+    fn table_refs_find_tables_and_aliases() {
+        let line = "SELECT * FROM trades t ASOF JOIN quotes AS q ON (ts), \"my table\"";
+        assert_eq!(table_refs(line), [("trades", Some("t")), ("quotes", Some("q"))]);
+        assert_eq!(table_refs("select * from a, b where"), [("a", None), ("b", None)]);
+        assert_eq!(qualified_table(line, "q"), "quotes");
+        assert_eq!(qualified_table(line, "trades"), "trades");
+    }
+
+    #[test]
+    fn expressions_offer_the_referenced_columns() {
+        let metadata = metadata();
+        let candidates = displays(metadata.expression_candidates("SELECT  FROM quotes", ""));
+        assert!(candidates.contains(&"bid".to_string()));
+        assert!(!candidates.contains(&"price".to_string()));
+
+        let candidates = displays(metadata.expression_candidates("SELECT p", "p"));
+        assert!(candidates.contains(&"price".to_string()));
+        assert!(candidates.contains(&"partition by".to_string()));
+
+        let avg = metadata.expression_candidates("SELECT av", "av");
+        assert!(avg.iter().any(|p| p.display == "avg" && p.replacement == "avg("));
+    }
+
+    #[test]
+    fn hints_show_column_types_and_signatures() {
+        let metadata = metadata();
+        let hint = |line: &str| {
+            let tokens = sql::tokenize(line);
+            metadata.column_hint(line, &tokens).or_else(|| metadata.signature_hint(line, &tokens))
+        };
+        assert_eq!(hint("SELECT ts FROM trades WHERE symbol").as_deref(), Some("  SYMBOL"));
+        assert_eq!(hint("SELECT q.ts FROM quotes q WHERE q.ts").as_deref(), Some("  TIMESTAMP, designated timestamp"));
+        assert_eq!(hint("SELECT avg(price").as_deref(), Some("  DOUBLE"));
+        assert_eq!(hint("SELECT avg(").as_deref(), Some("  avg(D) | avg(L)"));
+        assert_eq!(hint("SELECT avg(price) "), None);
+        assert_eq!(hint("SELECT unknown"), None);
+    }
+
+    #[test]
+    fn input_is_complete_once_a_semicolon_ends_it() {
+        assert!(is_complete(""));
+        assert!(is_complete("SELECT 1;"));
+        assert!(is_complete("SELECT 1;\nSELECT 2; -- done"));
+        assert!(is_complete("\\format csv"));
+        assert!(!is_complete("SELECT 1"));
+        assert!(!is_complete("SELECT 1; SELECT"));
+        assert!(!is_complete("SELECT 'a;"));
+        assert!(!is_complete("SELECT 1 /* ; */"));
+    }
+}
@@ -0,0 +1,211 @@
+// src/credentials.rs
+
+use crate::config::Profile;
+use crate::db::ConnectionParams;
+use anyhow::{anyhow, bail, Context, Result};
+use std::io::IsTerminal;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+/// Password used when no user or password is configured at all, matching
+/// QuestDB's out-of-the-box `admin` account.
+const DEFAULT_PASSWORD: &str = "quest";
+
+/// Finds the password for a connection, trying in order:
+///
+/// 1. a password given on the command line, in the URL or in the profile
+/// 2. the profile's or `--password-command` secret helper
+/// 3. `QDB_PASSWORD`, then `PGPASSWORD`
+/// 4. a matching line in `$PGPASSFILE` or `~/.pgpass`
+/// 5. QuestDB's default password, if the user isn't configured either
+/// 6. an interactive prompt, when stdin is a terminal
+///
+/// `prompt` skips straight to the prompt after the explicit password.
+pub fn password(profile: &Profile, params: &ConnectionParams, prompt: bool) -> Result<Option<String>> {
+    if let Some(password) = &profile.password {
+        return Ok(Some(password.clone()));
+    }
+    if prompt {
+        return prompt_password(params).map(Some);
+    }
+    if let Some(command) = &profile.password_command {
+        return run_password_command(command).map(Some);
+    }
+    for var in ["QDB_PASSWORD", "PGPASSWORD"] {
+        if let Ok(password) = std::env::var(var) {
+            return Ok(Some(password));
+        }
+    }
+    if let Some(password) = pgpass_password(params)? {
+        return Ok(Some(password));
+    }
+    if profile.user.is_none() {
+        return Ok(Some(DEFAULT_PASSWORD.to_string()));
+    }
+    if std::io::stdin().is_terminal() {
+        return prompt_password(params).map(Some);
+    }
+    Ok(None)
+}
+
+fn prompt_password(params: &ConnectionParams) -> Result<String> {
+    rpassword::prompt_password(format!("Password for {}@{}: ", params.user, params.host))
+        .context("cannot read password")
+}
+
+/// Runs a secret helper through the shell and takes the first line it prints.
+fn run_password_command(command: &str) -> Result<String> {
+    let mut shell = if cfg!(windows) {
+        let mut cmd = Command::new("cmd");
+        cmd.arg("/C");
+        cmd
+    } else {
+        let mut cmd = Command::new("sh");
+        cmd.arg("-c");
+        cmd
+    };
+    // stdin and stderr stay attached so the helper can ask for a passphrase
+    let output = shell
+        .arg(command)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::inherit())
+        .output()
+        .with_context(|| format!("cannot run password command '{}'", command))?;
+    if !output.status.success() {
+        bail!("password command '{}' failed with {}", command, output.status);
+    }
+    let stdout = String::from_utf8(output.stdout).map_err(|_| anyhow!("password command printed invalid UTF-8"))?;
+    let password = stdout.lines().next().unwrap_or("");
+    if password.is_empty() {
+        bail!("password command '{}' printed no password", command);
+    }
+    Ok(password.to_string())
+}
+
+fn pgpass_path() -> PathBuf {
+    match std::env::var_os("PGPASSFILE") {
+        Some(path) if !path.is_empty() => PathBuf::from(path),
+        _ => PathBuf::from(shellexpand::tilde("~/.pgpass").into_owned()),
+    }
+}
+
+/// Looks the connection up in a `.pgpass` file: lines of
+/// `host:port:database:user:password`, where `*` matches anything and `\`
+/// escapes `:` and `\`. Like libpq, a file others can read is ignored.
+fn pgpass_password(params: &ConnectionParams) -> Result<Option<String>> {
+    let path = pgpass_path();
+    let content = match std::fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e).with_context(|| format!("cannot read password file '{}'", path.display())),
+    };
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = std::fs::metadata(&path)?.permissions().mode();
+        if mode & 0o077 != 0 {
+            eprintln!(
+                "Warning: password file '{}' has group or world access; permissions should be 0600 or less",
+                path.display()
+            );
+            return Ok(None);
+        }
+    }
+
+    let port = params.port.to_string();
+    let wanted = [params.host.as_str(), port.as_str(), params.dbname.as_str(), params.user.as_str()];
+    for line in content.lines() {
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let fields = split_pgpass_line(line);
+        if fields.len() != 5 {
+            continue;
+        }
+        let matches = fields[..4]
+            .iter()
+            .zip(wanted)
+            .all(|(field, value)| field == "*" || field == value);
+        if matches {
+            return Ok(Some(fields[4].clone()));
+        }
+    }
+    Ok(None)
+}
+
+fn split_pgpass_line(line: &str) -> Vec<String> {
+    let mut fields = vec![String::new()];
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                if let Some(escaped) = chars.next() {
+                    fields.last_mut().unwrap().push(escaped);
+                }
+            }
+            ':' => fields.push(String::new()),
+            c => fields.last_mut().unwrap().push(c),
+        }
+    }
+    fields
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pgpass_lines_split_on_unescaped_colons() {
+        assert_eq!(split_pgpass_line("host:8812:qdb:admin:quest"), ["host", "8812", "qdb", "admin", "quest"]);
+        assert_eq!(split_pgpass_line(r"*:*:*:admin:a\:b\\c"), ["*", "*", "*", "admin", r"a:b\c"]);
+    }
+
+    #[test]
+    fn password_lookup_order() {
+        // the environment is process-wide, so every lookup that reads it
+        // runs in this one test
+        let dir = std::env::temp_dir().join(format!("qdb-credentials-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let pgpass = dir.join("pgpass");
+        std::fs::write(&pgpass, "# comment\nother:*:*:*:wrong\nlocalhost:8812:qdb:admin:from-pgpass\n").unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(&pgpass, std::fs::Permissions::from_mode(0o600)).unwrap();
+        }
+        std::env::set_var("PGPASSFILE", &pgpass);
+        std::env::remove_var("QDB_PASSWORD");
+        std::env::remove_var("PGPASSWORD");
+
+        let mut profile = Profile { user: Some("admin".to_string()), ..Profile::default() };
+        let params = profile.connection_params();
+        let lookup = |profile: &Profile| password(profile, &params, false).unwrap();
+
+        assert_eq!(lookup(&profile).as_deref(), Some("from-pgpass"));
+        std::env::set_var("PGPASSWORD", "from-pgpassword");
+        assert_eq!(lookup(&profile).as_deref(), Some("from-pgpassword"));
+        std::env::set_var("QDB_PASSWORD", "from-qdb-password");
+        assert_eq!(lookup(&profile).as_deref(), Some("from-qdb-password"));
+        profile.password_command = Some("echo from-command; echo ignored".to_string());
+        assert_eq!(lookup(&profile).as_deref(), Some("from-command"));
+        profile.password = Some("explicit".to_string());
+        assert_eq!(lookup(&profile).as_deref(), Some("explicit"));
+
+        // without a configured user, QuestDB's default password comes last
+        std::env::remove_var("QDB_PASSWORD");
+        std::env::remove_var("PGPASSWORD");
+        std::env::set_var("PGPASSFILE", dir.join("missing"));
+        assert_eq!(lookup(&Profile::default()).as_deref(), Some(DEFAULT_PASSWORD));
+
+        std::env::remove_var("PGPASSFILE");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn failing_password_command_is_an_error() {
+        assert!(run_password_command("exit 3").is_err());
+        assert!(run_password_command("true").is_err());
+    }
+}
@@ -1,39 +1,414 @@
 // src/db.rs
 
-use tokio_postgres::{Client, NoTls, Error};
+use crate::http::HttpClient;
+use anyhow::{anyhow, Context, Result};
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::crypto::{self, WebPkiSupportedAlgorithms};
+use rustls::pki_types::{CertificateDer, PrivateKeyDer, ServerName, UnixTime};
+use rustls::{ClientConfig, DigitallySignedStruct, RootCertStore, SignatureScheme};
+use serde::Deserialize;
+use std::fmt;
+use std::fs::File;
+use std::io::BufReader;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncWrite};
 use tokio_postgres::config::SslMode;
+use tokio_postgres::tls::MakeTlsConnect;
+use tokio_postgres::{Client, NoTls, SimpleQueryMessage};
+use tokio_postgres_rustls::MakeRustlsConnect;
+
+/// How queries reach the server: PGWire, or the HTTP `/exec` endpoint.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Transport {
+    #[default]
+    Pg,
+    Http,
+}
+
+impl Transport {
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "pg" | "pgwire" => Some(Transport::Pg),
+            "http" => Some(Transport::Http),
+            _ => None,
+        }
+    }
+
+    /// The port QuestDB serves this transport on out of the box.
+    pub fn default_port(&self) -> u16 {
+        match self {
+            Transport::Pg => 8812,
+            Transport::Http => 9000,
+        }
+    }
+}
+
+impl fmt::Display for Transport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Transport::Pg => f.write_str("pg"),
+            Transport::Http => f.write_str("http"),
+        }
+    }
+}
+
+/// TLS settings for the connection.
+#[derive(Debug, Clone, Default)]
+pub struct TlsOptions {
+    pub enabled: bool,
+    /// PEM bundle of CA certificates to trust instead of the built-in roots.
+    pub ca_file: Option<String>,
+    /// PEM client certificate chain and key for mutual TLS.
+    pub client_cert: Option<String>,
+    pub client_key: Option<String>,
+    /// Server name to send (SNI) and verify instead of the host.
+    pub server_name: Option<String>,
+    /// Skip certificate verification entirely.
+    pub allow_invalid_cert: bool,
+}
+
+/// Attempts made to re-open a lost connection before giving up.
+const RECONNECT_ATTEMPTS: u32 = 8;
+/// Wait before the second attempt; doubled after every failure.
+const RECONNECT_INITIAL_DELAY: Duration = Duration::from_millis(250);
+const RECONNECT_MAX_DELAY: Duration = Duration::from_secs(8);
+
+/// Everything needed to open a connection.
+#[derive(Debug, Clone)]
+pub struct ConnectionParams {
+    pub transport: Transport,
+    pub host: String,
+    pub port: u16,
+    pub user: String,
+    pub password: Option<String>,
+    pub dbname: String,
+    pub tls: TlsOptions,
+    /// Give up if connecting takes longer than this.
+    pub connect_timeout: Option<Duration>,
+}
+
+/// A connection attempt or statement that ran out of time.
+#[derive(Debug)]
+pub struct Timeout {
+    what: &'static str,
+    limit: Duration,
+}
+
+impl Timeout {
+    pub fn new(what: &'static str, limit: Duration) -> Self {
+        Self { what, limit }
+    }
+}
+
+impl fmt::Display for Timeout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} timed out after {:?}", self.what, self.limit)
+    }
+}
+
+impl std::error::Error for Timeout {}
+
+/// The client of an open connection, for either transport.
+#[derive(Clone)]
+pub enum Backend {
+    Pg(Arc<Client>),
+    Http(HttpClient),
+}
 
 /// Connects to the QuestDB database with the given parameters.
-pub async fn connect_to_db(
-    host: &str,
-    port: u16,
-    user: &str,
-    password: &str,
-    dbname: &str,
-    use_tls: bool,
-    _allow_invalid_cert: bool, // Prefixed with underscore to suppress unused variable warning
-) -> Result<Client, Error> {
+pub async fn connect_to_db(params: &ConnectionParams) -> Result<Backend> {
+    let open = async {
+        match params.transport {
+            Transport::Pg => Ok(Backend::Pg(Arc::new(connect(params).await?))),
+            Transport::Http => Ok(Backend::Http(HttpClient::connect(params).await?)),
+        }
+    };
+    match params.connect_timeout {
+        Some(limit) => tokio::time::timeout(limit, open)
+            .await
+            .map_err(|_| Timeout::new("connecting", limit))?,
+        None => open.await,
+    }
+}
+
+async fn connect(params: &ConnectionParams) -> Result<Client> {
+    let tls = &params.tls;
     let mut config = tokio_postgres::Config::new();
-    config.host(host)
-          .port(port)
-          .user(user)
-          .password(password)
-          .dbname(dbname);
+    config.host(&params.host)
+          .port(params.port)
+          .user(&params.user)
+          .dbname(&params.dbname);
+    if let Some(password) = &params.password {
+        config.password(password);
+    }
 
-    if use_tls {
+    let client = if tls.enabled {
         config.ssl_mode(SslMode::Require);
-        // Additional TLS configuration can be added here if needed
+        let (client, connection) = config.connect(tls_connector(tls)?).await?;
+        tokio::spawn(async move {
+            if let Err(e) = connection.await {
+                eprintln!("Connection error: {}", e);
+            }
+        });
+        client
     } else {
         config.ssl_mode(SslMode::Disable);
+        let (client, connection) = config.connect(NoTls).await?;
+        tokio::spawn(async move {
+            if let Err(e) = connection.await {
+                eprintln!("Connection error: {}", e);
+            }
+        });
+        client
+    };
+
+    Ok(client)
+}
+
+/// An open connection together with the parameters it was opened with,
+/// so that it can be re-opened when the server goes away.
+#[derive(Clone)]
+pub struct Connection {
+    pub params: ConnectionParams,
+    pub backend: Backend,
+}
+
+impl Connection {
+    pub async fn open(params: ConnectionParams) -> Result<Self> {
+        let backend = connect_to_db(&params).await?;
+        Ok(Self { params, backend })
+    }
+
+    /// `host:port` of the server, for messages.
+    pub fn address(&self) -> String {
+        format!("{}:{}", self.params.host, self.params.port)
     }
 
-    let (client, connection) = config.connect(NoTls).await?;
+    /// Whether the server went away. HTTP requests don't share a session,
+    /// so there is nothing to lose between them.
+    pub fn is_closed(&self) -> bool {
+        match &self.backend {
+            Backend::Pg(client) => client.is_closed(),
+            Backend::Http(_) => false,
+        }
+    }
 
-    tokio::spawn(async move {
-        if let Err(e) = connection.await {
-            eprintln!("Connection error: {}", e);
+    /// Runs a query and returns the first column of every row as text,
+    /// for metadata lookups.
+    pub async fn query_strings(&self, query: &str) -> Result<Vec<String>> {
+        match &self.backend {
+            Backend::Pg(client) => {
+                let rows = client.query(query, &[]).await?;
+                Ok(rows.iter().filter_map(|row| row.get::<_, Option<String>>(0)).collect())
+            }
+            Backend::Http(client) => {
+                let result = client.exec(query).await?;
+                Ok(result
+                    .dataset
+                    .iter()
+                    .filter_map(|row| row.first()?.as_str().map(str::to_string))
+                    .collect())
+            }
         }
-    });
+    }
 
-    Ok(client)
+    /// Runs a query and returns every value of every row as text, with
+    /// NULL as None.
+    pub async fn query_text(&self, query: &str) -> Result<Vec<Vec<Option<String>>>> {
+        match &self.backend {
+            Backend::Pg(client) => Ok(client
+                .simple_query(query)
+                .await?
+                .iter()
+                .filter_map(|message| match message {
+                    SimpleQueryMessage::Row(row) => Some((0..row.len()).map(|i| row.get(i).map(str::to_string)).collect()),
+                    _ => None,
+                })
+                .collect()),
+            Backend::Http(client) => Ok(client
+                .exec(query)
+                .await?
+                .dataset
+                .iter()
+                .map(|row| {
+                    row.iter()
+                        .map(|value| match value {
+                            serde_json::Value::Null => None,
+                            serde_json::Value::String(s) => Some(s.clone()),
+                            other => Some(other.to_string()),
+                        })
+                        .collect()
+                })
+                .collect()),
+        }
+    }
+
+    /// Asks the server to cancel the query running on this connection.
+    /// The request goes over a new connection, so it needs the same TLS setup.
+    /// Over HTTP, dropping the request is what stops the query.
+    pub async fn cancel(&self) -> Result<()> {
+        let client = match &self.backend {
+            Backend::Pg(client) => client,
+            Backend::Http(_) => return Ok(()),
+        };
+        let token = client.cancel_token();
+        if self.params.tls.enabled {
+            token.cancel_query(tls_connector(&self.params.tls)?).await?;
+        } else {
+            token.cancel_query(NoTls).await?;
+        }
+        Ok(())
+    }
+
+    /// Re-opens the connection, retrying with exponential backoff.
+    /// Returns the number of attempts it took.
+    pub async fn reconnect(&mut self) -> Result<u32> {
+        let mut delay = RECONNECT_INITIAL_DELAY;
+        let mut attempt = 1;
+        loop {
+            match connect_to_db(&self.params).await {
+                Ok(backend) => {
+                    self.backend = backend;
+                    return Ok(attempt);
+                }
+                Err(e) if attempt >= RECONNECT_ATTEMPTS => {
+                    return Err(e.context(format!("giving up after {} attempts", attempt)));
+                }
+                Err(e) => {
+                    eprintln!("Reconnect attempt {} failed: {}. Retrying in {:?}...", attempt, e, delay);
+                    tokio::time::sleep(delay).await;
+                    delay = (delay * 2).min(RECONNECT_MAX_DELAY);
+                    attempt += 1;
+                }
+            }
+        }
+    }
+}
+
+fn tls_connector(tls: &TlsOptions) -> Result<TlsConnector> {
+    Ok(TlsConnector {
+        inner: MakeRustlsConnect::new(client_config(tls)?),
+        server_name: tls.server_name.clone(),
+    })
+}
+
+/// Builds the rustls client configuration from the TLS options.
+pub(crate) fn client_config(tls: &TlsOptions) -> Result<ClientConfig> {
+    let provider = Arc::new(crypto::ring::default_provider());
+    let builder = ClientConfig::builder_with_provider(provider.clone())
+        .with_safe_default_protocol_versions()?;
+
+    let builder = if tls.allow_invalid_cert {
+        builder
+            .dangerous()
+            .with_custom_certificate_verifier(Arc::new(AcceptAnyCert(provider.signature_verification_algorithms)))
+    } else {
+        let mut roots = RootCertStore::empty();
+        match &tls.ca_file {
+            Some(path) => {
+                let certs = load_certs(path)?;
+                if certs.is_empty() {
+                    return Err(anyhow!("no certificates found in CA file '{}'", path));
+                }
+                for cert in certs {
+                    roots
+                        .add(cert)
+                        .with_context(|| format!("invalid CA certificate in '{}'", path))?;
+                }
+            }
+            None => roots.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned()),
+        }
+        builder.with_root_certificates(roots)
+    };
+
+    let config = match (&tls.client_cert, &tls.client_key) {
+        (Some(cert), Some(key)) => builder
+            .with_client_auth_cert(load_certs(cert)?, load_key(key)?)
+            .context("invalid client certificate or key")?,
+        (None, None) => builder.with_no_client_auth(),
+        _ => return Err(anyhow!("a client certificate and key must be given together")),
+    };
+    Ok(config)
+}
+
+fn open_pem(path: &str) -> Result<BufReader<File>> {
+    let path = shellexpand::tilde(path).into_owned();
+    let file = File::open(&path).with_context(|| format!("cannot open '{}'", path))?;
+    Ok(BufReader::new(file))
+}
+
+fn load_certs(path: &str) -> Result<Vec<CertificateDer<'static>>> {
+    rustls_pemfile::certs(&mut open_pem(path)?)
+        .collect::<Result<Vec<_>, _>>()
+        .with_context(|| format!("cannot read certificates from '{}'", path))
+}
+
+fn load_key(path: &str) -> Result<PrivateKeyDer<'static>> {
+    rustls_pemfile::private_key(&mut open_pem(path)?)
+        .with_context(|| format!("cannot read private key from '{}'", path))?
+        .ok_or_else(|| anyhow!("no private key found in '{}'", path))
+}
+
+/// Wraps the rustls connector so the server name used for SNI and
+/// certificate verification can differ from the host we connect to.
+#[derive(Clone)]
+struct TlsConnector {
+    inner: MakeRustlsConnect,
+    server_name: Option<String>,
+}
+
+impl<S> MakeTlsConnect<S> for TlsConnector
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    type Stream = <MakeRustlsConnect as MakeTlsConnect<S>>::Stream;
+    type TlsConnect = <MakeRustlsConnect as MakeTlsConnect<S>>::TlsConnect;
+    type Error = <MakeRustlsConnect as MakeTlsConnect<S>>::Error;
+
+    fn make_tls_connect(&mut self, hostname: &str) -> Result<Self::TlsConnect, Self::Error> {
+        let hostname = self.server_name.as_deref().unwrap_or(hostname);
+        MakeTlsConnect::<S>::make_tls_connect(&mut self.inner, hostname)
+    }
+}
+
+/// Certificate verifier for `--allow-invalid-cert`: any server certificate
+/// is accepted, but handshake signatures are still checked.
+#[derive(Debug)]
+struct AcceptAnyCert(WebPkiSupportedAlgorithms);
+
+impl ServerCertVerifier for AcceptAnyCert {
+    fn verify_server_cert(
+        &self,
+        _end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        crypto::verify_tls12_signature(message, cert, dss, &self.0)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        crypto::verify_tls13_signature(message, cert, dss, &self.0)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.0.supported_schemes()
+    }
 }
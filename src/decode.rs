@@ -0,0 +1,606 @@
+// src/decode.rs
+
+use anyhow::{anyhow, bail, Result};
+use chrono::{Duration, NaiveDate, NaiveDateTime};
+use std::fmt;
+use tokio_postgres::types::{FromSql, Type};
+use tokio_postgres::Row;
+
+/// Raw bytes of a column value, exactly as QuestDB sent them.
+///
+/// tokio-postgres only knows how to decode standard PostgreSQL types, while
+/// QuestDB sends some of its own types (LONG256, GEOHASH, IPv4, arrays, ...)
+/// in encodings that don't always match PostgreSQL's. Grabbing the raw bytes
+/// lets us decode every column ourselves.
+pub struct RawValue<'a>(pub &'a [u8]);
+
+impl<'a> FromSql<'a> for RawValue<'a> {
+    fn from_sql(
+        _ty: &Type,
+        raw: &'a [u8],
+    ) -> Result<Self, Box<dyn std::error::Error + Sync + Send>> {
+        Ok(RawValue(raw))
+    }
+
+    fn accepts(_ty: &Type) -> bool {
+        true
+    }
+}
+
+/// Returns the raw bytes of column `i`, or `None` for SQL NULL.
+pub fn raw_column(row: &Row, i: usize) -> Result<Option<&[u8]>> {
+    Ok(row.try_get::<_, Option<RawValue>>(i)?.map(|raw| raw.0))
+}
+
+/// PostgreSQL epoch (2000-01-01) used by binary TIMESTAMP and DATE values.
+fn pg_epoch() -> NaiveDateTime {
+    NaiveDate::from_ymd_opt(2000, 1, 1)
+        .and_then(|d| d.and_hms_opt(0, 0, 0))
+        .expect("valid epoch")
+}
+
+/// Returns the value as text when it was sent in text format.
+fn as_text(raw: &[u8]) -> Option<&str> {
+    std::str::from_utf8(raw)
+        .ok()
+        .filter(|s| !s.is_empty() && s.bytes().all(|b| b.is_ascii_graphic() || b == b' '))
+}
+
+/// Decodes BYTE, SHORT, INT and LONG columns (INT2, INT4, INT8).
+///
+/// tokio-postgres asks for every result column in binary format, so numbers
+/// are always binary; text such as `12` would be indistinguishable from a
+/// binary INT2 by its length and isn't accepted.
+pub fn int(raw: &[u8]) -> Result<i64> {
+    match raw.len() {
+        1 => Ok(raw[0] as i8 as i64),
+        2 => Ok(i16::from_be_bytes([raw[0], raw[1]]) as i64),
+        4 => Ok(i32::from_be_bytes(raw.try_into()?) as i64),
+        8 => Ok(i64::from_be_bytes(raw.try_into()?)),
+        n => bail!("invalid integer value of {} bytes", n),
+    }
+}
+
+/// Decodes FLOAT columns (FLOAT4).
+pub fn float4(raw: &[u8]) -> Result<f32> {
+    match raw.len() {
+        4 => Ok(f32::from_be_bytes(raw.try_into()?)),
+        n => bail!("invalid float value of {} bytes", n),
+    }
+}
+
+/// Decodes DOUBLE columns (FLOAT8).
+pub fn float8(raw: &[u8]) -> Result<f64> {
+    match raw.len() {
+        8 => Ok(f64::from_be_bytes(raw.try_into()?)),
+        4 => Ok(f32::from_be_bytes(raw.try_into()?) as f64),
+        n => bail!("invalid double value of {} bytes", n),
+    }
+}
+
+/// Decodes BOOLEAN columns.
+pub fn boolean(raw: &[u8]) -> Result<bool> {
+    match raw {
+        [0] | b"f" | b"false" => Ok(false),
+        [1] | b"t" | b"true" => Ok(true),
+        _ => Err(anyhow!("invalid boolean value: {:?}", raw)),
+    }
+}
+
+/// Decodes VARCHAR, STRING, SYMBOL, CHAR, GEOHASH, IPv4 and anything else
+/// QuestDB sends as text.
+pub fn text(raw: &[u8]) -> String {
+    String::from_utf8_lossy(raw).into_owned()
+}
+
+/// Decodes TIMESTAMP and DATE columns, which QuestDB sends as TIMESTAMP.
+pub fn timestamp(raw: &[u8]) -> Result<NaiveDateTime> {
+    if raw.len() == 8 {
+        let micros = i64::from_be_bytes(raw.try_into()?);
+        return pg_epoch()
+            .checked_add_signed(Duration::microseconds(micros))
+            .ok_or_else(|| anyhow!("timestamp out of range: {}", micros));
+    }
+    parse_timestamp(as_text(raw).ok_or_else(|| anyhow!("invalid timestamp value"))?)
+}
+
+/// Parses an ISO 8601 timestamp, with a `T` or a space before the time.
+pub fn parse_timestamp(s: &str) -> Result<NaiveDateTime> {
+    let s = s.trim_end_matches('Z');
+    NaiveDateTime::parse_from_str(s, "%Y-%m-%dT%H:%M:%S%.f")
+        .or_else(|_| NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S%.f"))
+        .map_err(|e| anyhow!("invalid timestamp '{}': {}", s, e))
+}
+
+/// Decodes PostgreSQL DATE columns.
+pub fn date(raw: &[u8]) -> Result<NaiveDate> {
+    if raw.len() == 4 {
+        let days = i32::from_be_bytes(raw.try_into()?);
+        return pg_epoch()
+            .date()
+            .checked_add_signed(Duration::days(days as i64))
+            .ok_or_else(|| anyhow!("date out of range: {}", days));
+    }
+    let s = as_text(raw).ok_or_else(|| anyhow!("invalid date value"))?;
+    NaiveDate::parse_from_str(s, "%Y-%m-%d").map_err(|e| anyhow!("invalid date '{}': {}", s, e))
+}
+
+/// Decodes UUID columns.
+pub fn uuid(raw: &[u8]) -> Result<u128> {
+    if raw.len() == 16 {
+        return Ok(u128::from_be_bytes(raw.try_into()?));
+    }
+    let s = as_text(raw).ok_or_else(|| anyhow!("invalid uuid value"))?;
+    u128::from_str_radix(&s.replace('-', ""), 16).map_err(|e| anyhow!("invalid uuid '{}': {}", s, e))
+}
+
+/// Decodes NUMERIC columns. QuestDB uses NUMERIC for LONG256 and sends it as
+/// `0x`-prefixed hex text; genuine binary NUMERIC values are decoded too.
+pub fn numeric(raw: &[u8]) -> Result<String> {
+    if let Some(s) = as_text(raw) {
+        return Ok(s.to_string());
+    }
+    if raw.len() < 8 {
+        bail!("invalid numeric value");
+    }
+    let ndigits = be_i16(raw, 0)? as usize;
+    let weight = be_i16(raw, 2)? as i32;
+    let sign = be_i16(raw, 4)? as u16;
+    let dscale = be_i16(raw, 6)? as usize;
+    if sign == 0xC000 {
+        return Ok("NaN".to_string());
+    }
+    let digits = (0..ndigits)
+        .map(|k| be_i16(raw, 8 + 2 * k))
+        .collect::<Result<Vec<_>>>()?;
+    let digit = |k: i32| {
+        if k < 0 {
+            0
+        } else {
+            digits.get(k as usize).copied().unwrap_or(0)
+        }
+    };
+
+    let mut out = String::new();
+    if sign == 0x4000 {
+        out.push('-');
+    }
+    if weight < 0 {
+        out.push('0');
+    } else {
+        for k in 0..=weight {
+            if k == 0 {
+                out.push_str(&digit(k).to_string());
+            } else {
+                out.push_str(&format!("{:04}", digit(k)));
+            }
+        }
+    }
+    if dscale > 0 {
+        let mut frac = String::new();
+        let mut k = weight + 1;
+        while frac.len() < dscale {
+            frac.push_str(&format!("{:04}", digit(k)));
+            k += 1;
+        }
+        frac.truncate(dscale);
+        out.push('.');
+        out.push_str(&frac);
+    }
+    Ok(out)
+}
+
+/// Decodes INTERVAL columns into PostgreSQL's textual interval style.
+pub fn interval(raw: &[u8]) -> Result<String> {
+    if raw.len() != 16 {
+        return as_text(raw)
+            .map(str::to_string)
+            .ok_or_else(|| anyhow!("invalid interval value"));
+    }
+    let micros = i64::from_be_bytes(raw[0..8].try_into()?);
+    let days = i32::from_be_bytes(raw[8..12].try_into()?);
+    let months = i32::from_be_bytes(raw[12..16].try_into()?);
+
+    let mut parts = Vec::new();
+    let (years, months) = (months / 12, months % 12);
+    if years != 0 {
+        parts.push(format!("{} year{}", years, if years.abs() == 1 { "" } else { "s" }));
+    }
+    if months != 0 {
+        parts.push(format!("{} mon{}", months, if months.abs() == 1 { "" } else { "s" }));
+    }
+    if days != 0 {
+        parts.push(format!("{} day{}", days, if days.abs() == 1 { "" } else { "s" }));
+    }
+    if micros != 0 || parts.is_empty() {
+        let sign = if micros < 0 { "-" } else { "" };
+        let total = micros.unsigned_abs();
+        let secs = total / 1_000_000;
+        let frac = total % 1_000_000;
+        let mut time = format!("{}{:02}:{:02}:{:02}", sign, secs / 3600, secs / 60 % 60, secs % 60);
+        if frac != 0 {
+            time.push_str(format!(".{:06}", frac).trim_end_matches('0'));
+        }
+        parts.push(time);
+    }
+    Ok(parts.join(" "))
+}
+
+/// A (possibly multi-dimensional) numeric array, such as QuestDB's `DOUBLE[]`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Array {
+    pub dims: Vec<usize>,
+    pub elems: Vec<Option<f64>>,
+}
+
+impl Array {
+    /// Converts the array into nested JSON arrays.
+    pub fn to_json(&self) -> serde_json::Value {
+        fn nest(dims: &[usize], elems: &[Option<f64>]) -> serde_json::Value {
+            match dims.split_first() {
+                None => serde_json::Value::Array(Vec::new()),
+                Some((_, [])) => serde_json::Value::Array(
+                    elems
+                        .iter()
+                        .map(|e| e.and_then(serde_json::Number::from_f64).map_or(serde_json::Value::Null, serde_json::Value::Number))
+                        .collect(),
+                ),
+                Some((&len, rest)) => {
+                    let stride = rest.iter().product::<usize>();
+                    serde_json::Value::Array(
+                        (0..len)
+                            .map(|k| nest(rest, sub_array(elems, k, stride)))
+                            .collect(),
+                    )
+                }
+            }
+        }
+        nest(&self.dims, &self.elems)
+    }
+}
+
+impl fmt::Display for Array {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fn nest(f: &mut fmt::Formatter<'_>, dims: &[usize], elems: &[Option<f64>]) -> fmt::Result {
+            f.write_str("[")?;
+            match dims.split_first() {
+                None => {}
+                Some((_, [])) => {
+                    for (k, e) in elems.iter().enumerate() {
+                        if k > 0 {
+                            f.write_str(",")?;
+                        }
+                        match e {
+                            Some(v) => write!(f, "{}", v)?,
+                            None => f.write_str("null")?,
+                        }
+                    }
+                }
+                Some((&len, rest)) => {
+                    let stride = rest.iter().product::<usize>();
+                    for k in 0..len {
+                        if k > 0 {
+                            f.write_str(",")?;
+                        }
+                        nest(f, rest, sub_array(elems, k, stride))?;
+                    }
+                }
+            }
+            f.write_str("]")
+        }
+        nest(f, &self.dims, &self.elems)
+    }
+}
+
+/// The `k`th sub-array of `stride` elements. Decoded arrays always hold
+/// as many elements as their dimensions say, but the fields are public, so
+/// a short one yields empty sub-arrays rather than a panic.
+fn sub_array(elems: &[Option<f64>], k: usize, stride: usize) -> &[Option<f64>] {
+    elems.get(k * stride..(k + 1) * stride).unwrap_or_default()
+}
+
+/// Decodes numeric array columns (`DOUBLE[]` and friends), in either the
+/// PostgreSQL binary array encoding or the `{1,2,3}` text encoding.
+pub fn array(raw: &[u8]) -> Result<Array> {
+    if let Some(s) = as_text(raw).filter(|s| s.starts_with('{')) {
+        return parse_text_array(s);
+    }
+    if raw.len() < 12 {
+        bail!("invalid array value");
+    }
+    let ndim = usize::try_from(be_i32(raw, 0)?).map_err(|_| anyhow!("invalid array dimensions"))?;
+    let elem_type = Type::from_oid(be_i32(raw, 8)? as u32).unwrap_or(Type::FLOAT8);
+    let mut offset = 12;
+    // sizes come from the wire; check them against the data before allocating
+    if ndim.checked_mul(8).is_none_or(|n| n > raw.len() - offset) {
+        bail!("truncated array value");
+    }
+    let mut dims = Vec::with_capacity(ndim);
+    for _ in 0..ndim {
+        let dim = be_i32(raw, offset)?;
+        dims.push(usize::try_from(dim).map_err(|_| anyhow!("invalid array dimension {}", dim))?);
+        offset += 8; // length + lower bound
+    }
+    let count = if ndim == 0 {
+        0
+    } else {
+        dims.iter()
+            .try_fold(1usize, |count, &dim| count.checked_mul(dim))
+            .ok_or_else(|| anyhow!("invalid array dimensions {:?}", dims))?
+    };
+    // every element takes at least its 4-byte length
+    if count > (raw.len() - offset) / 4 {
+        bail!("truncated array value");
+    }
+    let mut elems = Vec::with_capacity(count);
+    for _ in 0..count {
+        let len = be_i32(raw, offset)?;
+        offset += 4;
+        if len < 0 {
+            elems.push(None);
+            continue;
+        }
+        let bytes = raw
+            .get(offset..offset + len as usize)
+            .ok_or_else(|| anyhow!("truncated array value"))?;
+        offset += len as usize;
+        let v = match elem_type {
+            Type::FLOAT4 => float4(bytes)? as f64,
+            Type::INT2 | Type::INT4 | Type::INT8 => int(bytes)? as f64,
+            _ => float8(bytes)?,
+        };
+        elems.push(Some(v));
+    }
+    Ok(Array { dims, elems })
+}
+
+/// Decodes an array sent as nested JSON arrays, as the HTTP API does.
+pub fn json_array(json: &serde_json::Value) -> Result<Array> {
+    parse_text_array(&json.to_string())
+}
+
+fn parse_text_array(s: &str) -> Result<Array> {
+    let mut dims: Vec<usize> = Vec::new();
+    let mut counts: Vec<usize> = Vec::new();
+    let mut elems = Vec::new();
+    let mut token = String::new();
+
+    fn flush(token: &mut String, elems: &mut Vec<Option<f64>>, counts: &mut [usize]) -> Result<()> {
+        let t = token.trim();
+        if !t.is_empty() {
+            elems.push(if t.eq_ignore_ascii_case("null") {
+                None
+            } else {
+                Some(t.parse::<f64>().map_err(|e| anyhow!("invalid array element '{}': {}", t, e))?)
+            });
+            if let Some(c) = counts.last_mut() {
+                *c += 1;
+            }
+        }
+        token.clear();
+        Ok(())
+    }
+
+    for ch in s.chars() {
+        match ch {
+            '{' | '[' => {
+                if let Some(c) = counts.last_mut() {
+                    *c += 1;
+                }
+                counts.push(0);
+                if dims.len() < counts.len() {
+                    dims.push(0);
+                }
+            }
+            '}' | ']' => {
+                flush(&mut token, &mut elems, &mut counts)?;
+                let depth = counts.len();
+                let n = counts.pop().ok_or_else(|| anyhow!("unbalanced array '{}'", s))?;
+                dims[depth - 1] = dims[depth - 1].max(n);
+            }
+            ',' => flush(&mut token, &mut elems, &mut counts)?,
+            c => token.push(c),
+        }
+    }
+    if elems.is_empty() {
+        dims.clear();
+    } else if dims.iter().try_fold(1usize, |count, &dim| count.checked_mul(dim)) != Some(elems.len()) {
+        bail!("ragged array '{}'", s);
+    }
+    Ok(Array { dims, elems })
+}
+
+/// Lowercase hex encoding of raw bytes.
+pub fn hex(raw: &[u8]) -> String {
+    raw.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn be_i16(raw: &[u8], offset: usize) -> Result<i16> {
+    raw.get(offset..offset + 2)
+        .map(|b| i16::from_be_bytes([b[0], b[1]]))
+        .ok_or_else(|| anyhow!("truncated value"))
+}
+
+fn be_i32(raw: &[u8], offset: usize) -> Result<i32> {
+    raw.get(offset..offset + 4)
+        .map(|b| i32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+        .ok_or_else(|| anyhow!("truncated value"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A binary NUMERIC: header, then base-10000 digits.
+    fn numeric_bytes(weight: i16, sign: u16, dscale: i16, digits: &[i16]) -> Vec<u8> {
+        let mut raw = Vec::new();
+        raw.extend((digits.len() as i16).to_be_bytes());
+        raw.extend(weight.to_be_bytes());
+        raw.extend(sign.to_be_bytes());
+        raw.extend(dscale.to_be_bytes());
+        for digit in digits {
+            raw.extend(digit.to_be_bytes());
+        }
+        raw
+    }
+
+    /// A binary array of FLOAT8 elements, `None` being NULL.
+    fn float8_array_bytes(dims: &[i32], elems: &[Option<f64>]) -> Vec<u8> {
+        let mut raw = Vec::new();
+        raw.extend((dims.len() as i32).to_be_bytes());
+        raw.extend(i32::from(elems.contains(&None)).to_be_bytes());
+        raw.extend((Type::FLOAT8.oid() as i32).to_be_bytes());
+        for dim in dims {
+            raw.extend(dim.to_be_bytes());
+            raw.extend(1i32.to_be_bytes());
+        }
+        for elem in elems {
+            match elem {
+                Some(v) => {
+                    raw.extend(8i32.to_be_bytes());
+                    raw.extend(v.to_be_bytes());
+                }
+                None => raw.extend((-1i32).to_be_bytes()),
+            }
+        }
+        raw
+    }
+
+    #[test]
+    fn numeric_keeps_scale_and_sign() {
+        assert_eq!(numeric(&numeric_bytes(0, 0x4000, 4, &[12, 3400])).unwrap(), "-12.3400");
+        assert_eq!(numeric(&numeric_bytes(1, 0, 1, &[1234, 5678, 9000])).unwrap(), "12345678.9");
+        assert_eq!(numeric(&numeric_bytes(2, 0, 0, &[1])).unwrap(), "100000000");
+        assert_eq!(numeric(&numeric_bytes(-1, 0, 4, &[12])).unwrap(), "0.0012");
+        assert_eq!(numeric(&numeric_bytes(0, 0xC000, 0, &[])).unwrap(), "NaN");
+    }
+
+    #[test]
+    fn numeric_passes_long256_text_through() {
+        assert_eq!(numeric(b"0x01ff").unwrap(), "0x01ff");
+        assert!(numeric(&[0, 1, 0]).is_err());
+    }
+
+    #[test]
+    fn interval_formats_like_postgres() {
+        let interval_bytes = |micros: i64, days: i32, months: i32| {
+            let mut raw = micros.to_be_bytes().to_vec();
+            raw.extend(days.to_be_bytes());
+            raw.extend(months.to_be_bytes());
+            raw
+        };
+        assert_eq!(
+            interval(&interval_bytes(3_723_500_000, 2, 14)).unwrap(),
+            "1 year 2 mons 2 days 01:02:03.5"
+        );
+        assert_eq!(interval(&interval_bytes(-1_000_000, 1, 0)).unwrap(), "1 day -00:00:01");
+        assert_eq!(interval(&interval_bytes(0, 0, 0)).unwrap(), "00:00:00");
+        assert_eq!(interval(b"3 days").unwrap(), "3 days");
+    }
+
+    #[test]
+    fn array_decodes_binary_2x2_with_null() {
+        let raw = float8_array_bytes(&[2, 2], &[Some(1.0), None, Some(3.0), Some(4.5)]);
+        let array = array(&raw).unwrap();
+        assert_eq!(array.dims, vec![2, 2]);
+        assert_eq!(array.elems, vec![Some(1.0), None, Some(3.0), Some(4.5)]);
+        assert_eq!(array.to_string(), "[[1,null],[3,4.5]]");
+        assert_eq!(array.to_json(), serde_json::json!([[1.0, null], [3.0, 4.5]]));
+    }
+
+    #[test]
+    fn array_decodes_empty_and_integer_arrays() {
+        let empty = array(&float8_array_bytes(&[], &[])).unwrap();
+        assert_eq!(empty, Array { dims: vec![], elems: vec![] });
+
+        let mut raw = Vec::new();
+        for word in [1, 0, Type::INT4.oid() as i32, 2, 1, 4, 7, 4, -7] {
+            raw.extend(word.to_be_bytes());
+        }
+        assert_eq!(array(&raw).unwrap().elems, vec![Some(7.0), Some(-7.0)]);
+
+        assert!(array(&float8_array_bytes(&[3], &[Some(1.0)])).is_err());
+    }
+
+    #[test]
+    fn array_rejects_bad_dimensions() {
+        let mut negative = float8_array_bytes(&[1], &[Some(1.0)]);
+        negative[12..16].copy_from_slice(&(-1i32).to_be_bytes());
+        assert!(array(&negative).is_err());
+
+        let mut huge = float8_array_bytes(&[1], &[Some(1.0)]);
+        huge[12..16].copy_from_slice(&i32::MAX.to_be_bytes());
+        assert!(array(&huge).is_err());
+
+        let mut many_dims = float8_array_bytes(&[1], &[Some(1.0)]);
+        many_dims[0..4].copy_from_slice(&(-3i32).to_be_bytes());
+        assert!(array(&many_dims).is_err());
+        many_dims[0..4].copy_from_slice(&1_000_000i32.to_be_bytes());
+        assert!(array(&many_dims).is_err());
+    }
+
+    #[test]
+    fn array_rejects_ragged_text_and_json() {
+        assert!(array(b"{{1,2},{3}}").is_err());
+        assert!(json_array(&serde_json::json!([[1.0], [2.0, 3.0]])).is_err());
+    }
+
+    #[test]
+    fn array_display_survives_short_elements() {
+        let array = Array { dims: vec![2, 2], elems: vec![Some(1.0)] };
+        assert_eq!(array.to_string(), "[[],[]]");
+        assert_eq!(array.to_json(), serde_json::json!([[], []]));
+    }
+
+    #[test]
+    fn array_parses_text_and_json() {
+        let array = array(b"{{1,2},{3,NULL}}").unwrap();
+        assert_eq!(array.dims, vec![2, 2]);
+        assert_eq!(array.elems, vec![Some(1.0), Some(2.0), Some(3.0), None]);
+
+        let json = json_array(&serde_json::json!([[1.5], [null]])).unwrap();
+        assert_eq!(json.dims, vec![2, 1]);
+        assert_eq!(json.elems, vec![Some(1.5), None]);
+    }
+
+    #[test]
+    fn int_and_floats_decode_binary_widths() {
+        assert_eq!(int(&[0xff]).unwrap(), -1);
+        assert_eq!(int(&256i16.to_be_bytes()).unwrap(), 256);
+        assert_eq!(int(&(-70000i32).to_be_bytes()).unwrap(), -70000);
+        assert_eq!(int(&i64::MAX.to_be_bytes()).unwrap(), i64::MAX);
+        assert!(int(b"123").is_err());
+
+        assert_eq!(float4(&1.5f32.to_be_bytes()).unwrap(), 1.5);
+        assert!(float4(b"2.5").is_err());
+        assert_eq!(float8(&2.5f64.to_be_bytes()).unwrap(), 2.5);
+        assert_eq!(float8(&0.25f32.to_be_bytes()).unwrap(), 0.25);
+        assert!(float8(b"2.5").is_err());
+    }
+
+    #[test]
+    fn timestamps_decode_binary_and_text() {
+        use chrono::Timelike;
+        let epoch = pg_epoch();
+        assert_eq!(timestamp(&0i64.to_be_bytes()).unwrap(), epoch);
+        assert_eq!(
+            timestamp(&1_500_000i64.to_be_bytes()).unwrap(),
+            epoch + Duration::microseconds(1_500_000)
+        );
+        let ts = parse_timestamp("2024-01-02T03:04:05.123456789Z").unwrap();
+        assert_eq!(ts.and_utc().timestamp_subsec_nanos(), 123_456_789);
+        assert_eq!(parse_timestamp("2024-01-02 03:04:05").unwrap(), ts.with_nanosecond(0).unwrap());
+        assert!(parse_timestamp("yesterday").is_err());
+    }
+
+    #[test]
+    fn date_uuid_and_boolean() {
+        assert_eq!(date(&(-1i32).to_be_bytes()).unwrap(), NaiveDate::from_ymd_opt(1999, 12, 31).unwrap());
+        assert_eq!(date(b"2024-02-29").unwrap(), NaiveDate::from_ymd_opt(2024, 2, 29).unwrap());
+        assert_eq!(uuid(b"00000000-0000-0000-0000-00000000000a").unwrap(), 10);
+        assert_eq!(uuid(&7u128.to_be_bytes()).unwrap(), 7);
+        assert!(boolean(&[1]).unwrap());
+        assert!(!boolean(b"f").unwrap());
+        assert!(boolean(b"yes").is_err());
+    }
+}
@@ -0,0 +1,211 @@
+// src/highlight.rs
+
+use crate::sql::{self, Token, TokenKind};
+use anyhow::{anyhow, bail, Result};
+use serde::Deserialize;
+use std::io::IsTerminal;
+
+/// Colours of the REPL's syntax highlighting, from the `[theme]` section
+/// of the config file:
+///
+/// ```toml
+/// [theme]
+/// keyword = "bold blue"
+/// string = "bright-green"
+/// comment = "none"
+/// ```
+///
+/// A style is a space-separated list of attributes (`bold`, `dim`,
+/// `italic`, `underline`, `reverse`), colours (`red`, `bright-red`,
+/// `on-red` for the background, or a 256-colour number) and raw SGR codes
+/// such as `1;38;5;208`. `none` leaves that kind of text unstyled.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Theme {
+    pub keyword: Option<String>,
+    pub function: Option<String>,
+    pub string: Option<String>,
+    pub number: Option<String>,
+    pub comment: Option<String>,
+    pub table: Option<String>,
+    pub column: Option<String>,
+    /// The parenthesis at the cursor and its match.
+    pub bracket: Option<String>,
+    /// Column types and function signatures shown after the cursor.
+    pub hint: Option<String>,
+}
+
+/// A theme resolved to SGR escape parameters; an empty one means no style.
+#[derive(Debug, Clone)]
+pub struct Styles {
+    keyword: String,
+    function: String,
+    string: String,
+    number: String,
+    comment: String,
+    table: String,
+    column: String,
+    bracket: String,
+    hint: String,
+}
+
+impl Styles {
+    /// The styles for the terminal, or None for plain text when `NO_COLOR`
+    /// is set or stdout isn't a terminal.
+    pub fn for_terminal(theme: &Theme) -> Result<Option<Styles>> {
+        let no_color = std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty());
+        if no_color || !std::io::stdout().is_terminal() {
+            return Ok(None);
+        }
+        let style = |name: &str, value: &Option<String>, default: &str| {
+            parse_style(value.as_deref().unwrap_or(default))
+                .map_err(|e| anyhow!("invalid theme.{}: {}", name, e))
+        };
+        Ok(Some(Styles {
+            keyword: style("keyword", &theme.keyword, "bold blue")?,
+            function: style("function", &theme.function, "cyan")?,
+            string: style("string", &theme.string, "green")?,
+            number: style("number", &theme.number, "magenta")?,
+            comment: style("comment", &theme.comment, "bright-black")?,
+            table: style("table", &theme.table, "yellow")?,
+            column: style("column", &theme.column, "bright-yellow")?,
+            bracket: style("bracket", &theme.bracket, "bold reverse")?,
+            hint: style("hint", &theme.hint, "bright-black")?,
+        }))
+    }
+
+    /// Colours a line of SQL. `is_table` and `is_column` tell the names to
+    /// style as such; `cursor` is the byte position of the cursor.
+    pub fn highlight(
+        &self,
+        line: &str,
+        cursor: usize,
+        is_table: impl Fn(&str) -> bool,
+        is_column: impl Fn(&str) -> bool,
+    ) -> String {
+        let tokens = sql::tokenize(line);
+        let brackets = matching_brackets(line, &tokens, cursor);
+        let mut out = String::with_capacity(line.len() * 2);
+        for (i, token) in tokens.iter().enumerate() {
+            let text = &line[token.start..token.end];
+            let name = match token.kind {
+                TokenKind::QuotedIdent => text.trim_matches('"'),
+                _ => text,
+            };
+            let style = match token.kind {
+                _ if brackets.is_some_and(|(open, close)| token.start == open || token.start == close) => {
+                    &self.bracket
+                }
+                TokenKind::Word if sql::is_keyword(text) => &self.keyword,
+                TokenKind::Word | TokenKind::QuotedIdent if is_table(name) => &self.table,
+                TokenKind::Word if is_call(line, &tokens[i + 1..]) => &self.function,
+                TokenKind::Word | TokenKind::QuotedIdent if is_column(name) => &self.column,
+                TokenKind::String => &self.string,
+                TokenKind::Number => &self.number,
+                TokenKind::Comment => &self.comment,
+                _ => "",
+            };
+            out.push_str(&paint(style, text));
+        }
+        out
+    }
+
+    pub fn hint(&self, hint: &str) -> String {
+        paint(&self.hint, hint)
+    }
+}
+
+fn paint(style: &str, text: &str) -> String {
+    if style.is_empty() {
+        text.to_string()
+    } else {
+        format!("\x1b[{}m{}\x1b[0m", style, text)
+    }
+}
+
+/// Whether the tokens following a word open its argument list.
+fn is_call(line: &str, rest: &[Token]) -> bool {
+    rest.iter()
+        .find(|t| t.kind != TokenKind::Whitespace)
+        .is_some_and(|t| t.kind == TokenKind::Punct && &line[t.start..t.end] == "(")
+}
+
+/// Positions of the parenthesis just before or under the cursor and of
+/// the one matching it, if any.
+fn matching_brackets(line: &str, tokens: &[Token], cursor: usize) -> Option<(usize, usize)> {
+    let parens: Vec<(usize, bool)> = tokens
+        .iter()
+        .filter(|t| t.kind == TokenKind::Punct)
+        .filter_map(|t| match &line[t.start..t.end] {
+            "(" => Some((t.start, true)),
+            ")" => Some((t.start, false)),
+            _ => None,
+        })
+        .collect();
+    let at = parens
+        .iter()
+        .position(|&(start, _)| start + 1 == cursor)
+        .or_else(|| parens.iter().position(|&(start, _)| start == cursor))?;
+    let mut depth = 0usize;
+    if parens[at].1 {
+        for &(start, open) in &parens[at + 1..] {
+            match (open, depth) {
+                (false, 0) => return Some((parens[at].0, start)),
+                (false, _) => depth -= 1,
+                (true, _) => depth += 1,
+            }
+        }
+    } else {
+        for &(start, open) in parens[..at].iter().rev() {
+            match (open, depth) {
+                (true, 0) => return Some((start, parens[at].0)),
+                (true, _) => depth -= 1,
+                (false, _) => depth += 1,
+            }
+        }
+    }
+    None
+}
+
+/// Turns a style such as `bold bright-red` into SGR parameters (`1;91`).
+fn parse_style(style: &str) -> Result<String> {
+    let mut codes = Vec::new();
+    for word in style.split_whitespace().map(str::to_lowercase) {
+        if word == "none" {
+            continue;
+        }
+        match sgr(&word) {
+            Some(code) => codes.push(code),
+            None => bail!("unknown style '{}'", word),
+        }
+    }
+    Ok(codes.join(";"))
+}
+
+fn sgr(word: &str) -> Option<String> {
+    const COLORS: [&str; 8] = ["black", "red", "green", "yellow", "blue", "magenta", "cyan", "white"];
+    let color = |name: &str| COLORS.iter().position(|&c| c == name);
+    let code = match word {
+        "bold" => 1,
+        "dim" => 2,
+        "italic" => 3,
+        "underline" => 4,
+        "reverse" => 7,
+        _ => {
+            if let Some(i) = color(word) {
+                30 + i
+            } else if let Some(i) = word.strip_prefix("bright-").and_then(color) {
+                90 + i
+            } else if let Some(i) = word.strip_prefix("on-").and_then(color) {
+                40 + i
+            } else if let Ok(n) = word.parse::<u8>() {
+                return Some(format!("38;5;{}", n));
+            } else if word.split(';').all(|c| c.parse::<u8>().is_ok()) {
+                return Some(word.to_string());
+            } else {
+                return None;
+            }
+        }
+    };
+    Some(code.to_string())
+}
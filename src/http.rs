@@ -0,0 +1,351 @@
+// src/http.rs
+
+use crate::db::{client_config, ConnectionParams};
+use crate::value::{ColumnMeta, ColumnType, Value};
+use anyhow::{anyhow, bail, Context, Result};
+use serde::Deserialize;
+
+/// Client for QuestDB's HTTP query endpoint, `/exec`.
+#[derive(Clone)]
+pub struct HttpClient {
+    client: reqwest::Client,
+    base_url: String,
+    user: String,
+    password: Option<String>,
+    /// Sent as a bearer token instead of the user and password.
+    token: Option<String>,
+}
+
+/// The rows of an `/exec` response. Statements without a result set
+/// (`{"ddl":"OK"}`, `{"dml":"OK"}`) have none.
+#[derive(Debug, Default)]
+pub struct ExecResult {
+    pub dataset: Vec<Vec<serde_json::Value>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ExecColumn {
+    name: String,
+    #[serde(rename = "type")]
+    type_name: String,
+}
+
+/// An error response; `/imp` reports its errors as `status`.
+#[derive(Debug, Deserialize)]
+struct ExecError {
+    #[serde(alias = "status")]
+    error: String,
+    position: Option<i64>,
+}
+
+/// Decodes every column of an `/exec` dataset row.
+pub fn decode_row(columns: &[ColumnMeta], row: &[serde_json::Value]) -> Result<Vec<Value>> {
+    columns
+        .iter()
+        .zip(row)
+        .map(|(column, json)| {
+            Value::from_json(column.column_type, json).map_err(|e| anyhow!("column '{}': {}", column.name, e))
+        })
+        .collect()
+}
+
+impl HttpClient {
+    /// Sets up the client and checks the server answers queries.
+    pub async fn connect(params: &ConnectionParams) -> Result<Self> {
+        let client = Self::new(params)?;
+        client.exec("SELECT 1").await?;
+        Ok(client)
+    }
+
+    /// Sets up the client without contacting the server.
+    pub fn new(params: &ConnectionParams) -> Result<Self> {
+        let tls = &params.tls;
+        let mut builder = reqwest::Client::builder();
+        if tls.enabled {
+            if tls.server_name.is_some() {
+                bail!("a TLS server name override is not supported over HTTP");
+            }
+            builder = builder.use_preconfigured_tls(client_config(tls)?);
+        }
+        if let Some(limit) = params.connect_timeout {
+            builder = builder.connect_timeout(limit);
+        }
+        let scheme = if tls.enabled { "https" } else { "http" };
+        let host = if params.host.contains(':') {
+            format!("[{}]", params.host)
+        } else {
+            params.host.clone()
+        };
+        Ok(Self {
+            client: builder.build().context("cannot set up the HTTP client")?,
+            base_url: format!("{}://{}:{}", scheme, host, params.port),
+            user: params.user.clone(),
+            password: params.password.clone(),
+            token: None,
+        })
+    }
+
+    /// Authenticates with a bearer token instead of the user and password.
+    pub fn with_token(mut self, token: String) -> Self {
+        self.token = Some(token);
+        self
+    }
+
+    /// A request to `path` on the server, with the credentials attached.
+    pub fn request(&self, method: reqwest::Method, path: &str) -> reqwest::RequestBuilder {
+        let request = self.client.request(method, format!("{}{}", self.base_url, path));
+        match (&self.token, &self.password) {
+            (Some(token), _) => request.bearer_auth(token),
+            (None, Some(password)) => request.basic_auth(&self.user, Some(password)),
+            (None, None) => request,
+        }
+    }
+
+    /// Sends a request and returns the status and body as they are.
+    pub async fn fetch(&self, request: reqwest::RequestBuilder) -> Result<(reqwest::StatusCode, String)> {
+        let response = request.send().await.map_err(|e| request_error(&self.base_url, e))?;
+        let status = response.status();
+        Ok((status, response.text().await?))
+    }
+
+    /// Sends a request and returns the response body, turning error
+    /// responses into errors carrying QuestDB's message.
+    pub async fn send(&self, request: reqwest::RequestBuilder) -> Result<String> {
+        let (status, body) = self.fetch(request).await?;
+        if !status.is_success() {
+            return Err(response_error(status, &body));
+        }
+        Ok(body)
+    }
+
+    /// Runs a statement through `/exec` and returns the whole result, for
+    /// small ones such as metadata lookups.
+    pub async fn exec(&self, query: &str) -> Result<ExecResult> {
+        let mut stream = self.exec_stream(query).await?;
+        let mut dataset = Vec::new();
+        while let Some(row) = stream.next_row().await? {
+            dataset.push(row);
+        }
+        Ok(ExecResult { dataset })
+    }
+
+    /// Runs a statement through `/exec` and reads the response up to the
+    /// first row, leaving the rows to be read one at a time.
+    pub async fn exec_stream(&self, query: &str) -> Result<ExecStream> {
+        let request = self.request(reqwest::Method::GET, "/exec").query(&[("query", query)]);
+        let response = request.send().await.map_err(|e| request_error(&self.base_url, e))?;
+        let status = response.status();
+        if !status.is_success() {
+            return Err(response_error(status, &response.text().await?));
+        }
+        let mut stream = ExecStream {
+            response,
+            buf: Vec::new(),
+            pos: 0,
+            eof: false,
+            columns: Vec::new(),
+            in_dataset: false,
+            first_row: true,
+        };
+        stream.expect(b'{').await?;
+        stream.read_fields().await?;
+        Ok(stream)
+    }
+}
+
+/// An `/exec` response read as it arrives: the fields before `dataset`
+/// (among them `columns`), then one row at a time, so that a result
+/// doesn't have to fit in memory.
+pub struct ExecStream {
+    response: reqwest::Response,
+    buf: Vec<u8>,
+    /// Start of the unread part of `buf`.
+    pos: usize,
+    eof: bool,
+    columns: Vec<ExecColumn>,
+    /// Whether `dataset` has been entered and not yet left.
+    in_dataset: bool,
+    /// Whether no row of `dataset` has been read yet.
+    first_row: bool,
+}
+
+impl ExecStream {
+    pub fn columns(&self) -> Vec<ColumnMeta> {
+        self.columns
+            .iter()
+            .map(|column| ColumnMeta {
+                name: column.name.clone(),
+                column_type: ColumnType::from_questdb(&column.type_name),
+            })
+            .collect()
+    }
+
+    /// The next row of `dataset`, or None once it has been read.
+    pub async fn next_row(&mut self) -> Result<Option<Vec<serde_json::Value>>> {
+        if !self.in_dataset {
+            return Ok(None);
+        }
+        let first = std::mem::replace(&mut self.first_row, false);
+        let row = match self.peek().await? {
+            b']' => None,
+            b',' if !first => {
+                self.pos += 1;
+                Some(self.value().await?)
+            }
+            _ if first => Some(self.value().await?),
+            other => bail!("invalid /exec response: unexpected '{}' in dataset", other as char),
+        };
+        match row {
+            Some(row) => Ok(Some(serde_json::from_slice(row).map_err(|e| anyhow!("invalid /exec row: {}", e))?)),
+            None => {
+                self.pos += 1;
+                self.in_dataset = false;
+                // the fields after the dataset (count, timings) are read and dropped
+                self.read_fields().await?;
+                Ok(None)
+            }
+        }
+    }
+
+    /// Reads `"key": value` pairs up to the end of the object or the
+    /// start of `dataset`.
+    async fn read_fields(&mut self) -> Result<()> {
+        loop {
+            match self.peek().await? {
+                b'}' => {
+                    self.pos += 1;
+                    return Ok(());
+                }
+                b',' => self.pos += 1,
+                _ => {}
+            }
+            let key: String = serde_json::from_slice(self.value().await?)
+                .map_err(|e| anyhow!("invalid /exec response: {}", e))?;
+            self.expect(b':').await?;
+            match key.as_str() {
+                "dataset" => {
+                    self.expect(b'[').await?;
+                    self.in_dataset = true;
+                    return Ok(());
+                }
+                "columns" => {
+                    self.columns = serde_json::from_slice(self.value().await?)
+                        .map_err(|e| anyhow!("invalid /exec columns: {}", e))?;
+                }
+                _ => {
+                    self.value().await?;
+                }
+            }
+        }
+    }
+
+    async fn expect(&mut self, byte: u8) -> Result<()> {
+        let found = self.peek().await?;
+        if found != byte {
+            bail!("invalid /exec response: expected '{}', found '{}'", byte as char, found as char);
+        }
+        self.pos += 1;
+        Ok(())
+    }
+
+    /// The next byte that isn't whitespace, without consuming it.
+    async fn peek(&mut self) -> Result<u8> {
+        loop {
+            if let Some(skip) = self.buf[self.pos..].iter().position(|b| !b.is_ascii_whitespace()) {
+                self.pos += skip;
+                return Ok(self.buf[self.pos]);
+            }
+            self.pos = self.buf.len();
+            if !self.fill().await? {
+                bail!("invalid /exec response: unexpected end");
+            }
+        }
+    }
+
+    /// Consumes the next complete JSON value and returns its bytes.
+    async fn value(&mut self) -> Result<&[u8]> {
+        self.peek().await?;
+        loop {
+            if let Some(len) = value_len(&self.buf[self.pos..], self.eof) {
+                let start = self.pos;
+                self.pos += len;
+                return Ok(&self.buf[start..self.pos]);
+            }
+            if !self.fill().await? {
+                bail!("invalid /exec response: unexpected end");
+            }
+        }
+    }
+
+    /// Reads the next chunk of the body; false at its end.
+    async fn fill(&mut self) -> Result<bool> {
+        if self.eof {
+            return Ok(false);
+        }
+        // keep only what hasn't been read yet
+        self.buf.drain(..self.pos);
+        self.pos = 0;
+        match self.response.chunk().await? {
+            Some(chunk) => self.buf.extend_from_slice(&chunk),
+            None => self.eof = true,
+        }
+        Ok(true)
+    }
+}
+
+/// The length of the JSON value at the start of `bytes`, or None if it
+/// continues past them. A number or literal at the very end is complete
+/// only at the end of the body.
+fn value_len(bytes: &[u8], eof: bool) -> Option<usize> {
+    let mut depth = 0usize;
+    let mut in_string = false;
+    let mut escaped = false;
+    for (i, &b) in bytes.iter().enumerate() {
+        if in_string {
+            match b {
+                _ if escaped => escaped = false,
+                b'\\' => escaped = true,
+                b'"' => {
+                    in_string = false;
+                    if depth == 0 {
+                        return Some(i + 1);
+                    }
+                }
+                _ => {}
+            }
+            continue;
+        }
+        match b {
+            b'"' => in_string = true,
+            b'[' | b'{' => depth += 1,
+            b']' | b'}' if depth > 0 => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i + 1);
+                }
+            }
+            b',' | b']' | b'}' | b':' if depth == 0 => return Some(i),
+            _ if depth == 0 && b.is_ascii_whitespace() => return Some(i),
+            _ => {}
+        }
+    }
+    (eof && depth == 0 && !in_string && !bytes.is_empty()).then_some(bytes.len())
+}
+
+fn response_error(status: reqwest::StatusCode, body: &str) -> anyhow::Error {
+    match serde_json::from_str::<ExecError>(body) {
+        Ok(ExecError { error, position: Some(position) }) => anyhow!("{} at position {}", error, position),
+        Ok(ExecError { error, position: None }) => anyhow!(error),
+        Err(_) => anyhow!("HTTP {}: {}", status, body.trim()),
+    }
+}
+
+/// reqwest only names the URL in its message; the reason (refused, DNS,
+/// TLS) is further down the source chain.
+fn request_error(base_url: &str, e: reqwest::Error) -> anyhow::Error {
+    let mut cause: &dyn std::error::Error = &e;
+    while let Some(source) = cause.source() {
+        cause = source;
+    }
+    anyhow!("cannot reach {}: {}", base_url, cause)
+}
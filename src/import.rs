@@ -0,0 +1,284 @@
+// src/import.rs
+
+use crate::cli::ImportArgs;
+use crate::db::{Backend, Connection};
+use crate::http::HttpClient;
+use crate::output::OutputSettings;
+use crate::value::{ColumnMeta, ColumnType, Value};
+use anyhow::{anyhow, bail, Context, Result};
+use reqwest::multipart::{Form, Part};
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::io::IsTerminal;
+use std::path::Path;
+use tokio::io::AsyncReadExt;
+
+/// Size of the pieces the file is uploaded in, and so of progress steps.
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// QuestDB's answer to an `/imp` upload, as requested with `fmt=json`.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ImportReport {
+    status: String,
+    location: Option<String>,
+    #[serde(default)]
+    rows_rejected: u64,
+    #[serde(default)]
+    rows_imported: u64,
+    partition_by: Option<String>,
+    timestamp: Option<String>,
+    #[serde(default)]
+    columns: Vec<ImportColumn>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ImportColumn {
+    name: String,
+    #[serde(rename = "type")]
+    type_name: String,
+    size: Option<i64>,
+    #[serde(default)]
+    errors: i64,
+}
+
+/// Uploads every file in `args` and prints QuestDB's report for each.
+pub async fn import_files(connection: &Connection, args: &ImportArgs, settings: &OutputSettings) -> Result<()> {
+    let client = match &connection.backend {
+        Backend::Http(client) => client,
+        Backend::Pg(_) => bail!("imports go through the HTTP API; connect with --transport http"),
+    };
+    let schema = schema(args)?;
+    // with several files going into one table, only the first replaces it
+    let mut imported = HashSet::new();
+    let mut rejected = 0;
+    for file in &args.files {
+        let table = match &args.table {
+            Some(table) => table.clone(),
+            None => table_name(file)?,
+        };
+        let overwrite = args.overwrite && !imported.contains(&table);
+        let report = upload(client, file, &table, schema.as_deref(), overwrite, args)
+            .await
+            .with_context(|| format!("cannot import '{}'", file))?;
+        print_report(&report, &table, settings)?;
+        rejected += report.rows_rejected;
+        imported.insert(table);
+    }
+    // the rows are in, but a script needs to know some are missing
+    if rejected > 0 {
+        bail!("the server rejected {} row{}", rejected, if rejected == 1 { "" } else { "s" });
+    }
+    Ok(())
+}
+
+/// The table a file goes into by default: its name without the extension.
+fn table_name(file: &str) -> Result<String> {
+    Path::new(file)
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .filter(|stem| !stem.is_empty())
+        .map(str::to_string)
+        .ok_or_else(|| anyhow!("cannot name a table after '{}'; use --table", file))
+}
+
+/// The `schema` form part: a JSON file as given, or built from the
+/// `COLUMN:TYPE[:PATTERN]` overrides.
+fn schema(args: &ImportArgs) -> Result<Option<String>> {
+    if let Some(path) = &args.schema_file {
+        let content = std::fs::read_to_string(shellexpand::tilde(path).as_ref())
+            .with_context(|| format!("cannot read schema file '{}'", path))?;
+        serde_json::from_str::<serde_json::Value>(&content)
+            .with_context(|| format!("invalid schema file '{}'", path))?;
+        return Ok(Some(content));
+    }
+    if args.schema.is_empty() {
+        return Ok(None);
+    }
+    let columns = args
+        .schema
+        .iter()
+        .map(|spec| {
+            // the pattern may contain colons itself, as in HH:mm:ss
+            let mut parts = spec.splitn(3, ':');
+            let (name, column_type) = match (parts.next(), parts.next()) {
+                (Some(name), Some(column_type)) if !name.is_empty() && !column_type.is_empty() => (name, column_type),
+                _ => bail!("invalid schema override '{}'; expected COLUMN:TYPE[:PATTERN]", spec),
+            };
+            let mut column = serde_json::json!({ "name": name, "type": column_type.to_uppercase() });
+            if let Some(pattern) = parts.next() {
+                column["pattern"] = serde_json::Value::from(pattern);
+            }
+            Ok(column)
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok(Some(serde_json::Value::Array(columns).to_string()))
+}
+
+async fn upload(
+    client: &HttpClient,
+    file: &str,
+    table: &str,
+    schema: Option<&str>,
+    overwrite: bool,
+    args: &ImportArgs,
+) -> Result<ImportReport> {
+    let path = shellexpand::tilde(file).into_owned();
+    let data = tokio::fs::File::open(&path).await?;
+    let size = data.metadata().await?.len();
+    let file_name = Path::new(&path)
+        .file_name()
+        .map_or_else(|| file.to_string(), |name| name.to_string_lossy().into_owned());
+
+    let body = reqwest::Body::wrap_stream(upload_stream(data, Progress::new(file, size)));
+    let mut form = Form::new();
+    if let Some(schema) = schema {
+        form = form.text("schema", schema.to_string());
+    }
+    form = form.part("data", Part::stream_with_length(body, size).file_name(file_name));
+
+    let mut query = vec![
+        ("fmt", "json".to_string()),
+        ("name", table.to_string()),
+        ("overwrite", overwrite.to_string()),
+    ];
+    if let Some(timestamp) = &args.timestamp {
+        query.push(("timestamp", timestamp.clone()));
+    }
+    if let Some(partition_by) = &args.partition_by {
+        query.push(("partitionBy", partition_by.to_uppercase()));
+    }
+    if let Some(delimiter) = args.delimiter {
+        query.push(("delimiter", delimiter.to_string()));
+    }
+    if let Some(atomicity) = &args.atomicity {
+        let atomicity = match atomicity.as_str() {
+            "skip-row" => "skipRow",
+            "abort" => "abort",
+            _ => "skipCol",
+        };
+        query.push(("atomicity", atomicity.to_string()));
+    }
+    if args.header {
+        query.push(("forceHeader", "true".to_string()));
+    }
+
+    let request = client.request(reqwest::Method::POST, "/imp").query(&query).multipart(form);
+    let body = client.send(request).await?;
+    let report: ImportReport =
+        serde_json::from_str(&body).map_err(|e| anyhow!("invalid /imp response: {}", e))?;
+    if report.status != "OK" {
+        bail!("{}", report.status);
+    }
+    Ok(report)
+}
+
+/// Reads the file in chunks, reporting progress as they are sent.
+fn upload_stream(
+    file: tokio::fs::File,
+    progress: Progress,
+) -> impl futures::Stream<Item = std::io::Result<Vec<u8>>> + Send + 'static {
+    futures::stream::try_unfold((file, progress), |(mut file, mut progress)| async move {
+        let mut chunk = vec![0; CHUNK_SIZE];
+        let n = file.read(&mut chunk).await?;
+        if n == 0 {
+            progress.finish();
+            return Ok(None);
+        }
+        chunk.truncate(n);
+        progress.advance(n as u64);
+        Ok(Some((chunk, (file, progress))))
+    })
+}
+
+/// Upload progress on stderr, shown only when stderr is a terminal.
+struct Progress {
+    file: String,
+    total: u64,
+    sent: u64,
+    shown: Option<u64>,
+    enabled: bool,
+}
+
+impl Progress {
+    fn new(file: &str, total: u64) -> Self {
+        Self {
+            file: file.to_string(),
+            total,
+            sent: 0,
+            shown: None,
+            enabled: std::io::stderr().is_terminal(),
+        }
+    }
+
+    fn advance(&mut self, bytes: u64) {
+        self.sent += bytes;
+        let percent = (self.sent * 100).checked_div(self.total).unwrap_or(100);
+        if self.enabled && self.shown != Some(percent) {
+            eprint!(
+                "\rUploading {}: {:>3}% ({} of {})",
+                self.file,
+                percent,
+                format_size(self.sent),
+                format_size(self.total)
+            );
+            self.shown = Some(percent);
+        }
+    }
+
+    fn finish(&self) {
+        if self.enabled && self.shown.is_some() {
+            eprintln!();
+        }
+    }
+}
+
+fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, UNITS[unit])
+}
+
+/// Prints the summary line and the per-column report in the output format.
+fn print_report(report: &ImportReport, table: &str, settings: &OutputSettings) -> Result<()> {
+    let mut summary = format!(
+        "Imported {} rows into '{}' ({} rejected)",
+        report.rows_imported,
+        report.location.as_deref().unwrap_or(table),
+        report.rows_rejected
+    );
+    if let Some(timestamp) = &report.timestamp {
+        summary.push_str(&format!(", timestamp {}", timestamp));
+    }
+    if let Some(partition_by) = &report.partition_by {
+        summary.push_str(&format!(", partitioned by {}", partition_by));
+    }
+    eprintln!("{}", summary);
+
+    let columns = [
+        ("column", ColumnType::Varchar),
+        ("type", ColumnType::Varchar),
+        ("size", ColumnType::Int),
+        ("errors", ColumnType::Long),
+    ]
+    .map(|(name, column_type)| ColumnMeta { name: name.to_string(), column_type });
+    let mut sink = settings.sink()?;
+    sink.begin(&columns)?;
+    for column in &report.columns {
+        sink.row(&[
+            Value::Text(column.name.clone()),
+            Value::Text(column.type_name.clone()),
+            column.size.map_or(Value::Null, Value::Int),
+            Value::Int(column.errors),
+        ])?;
+    }
+    sink.end()
+}
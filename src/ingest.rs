@@ -0,0 +1,739 @@
+// src/ingest.rs
+
+use crate::cli::IngestArgs;
+use crate::db::{ConnectionParams, Timeout};
+use crate::decode;
+use crate::http::HttpClient;
+use anyhow::{anyhow, bail, Context, Result};
+use base64::engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD};
+use base64::Engine;
+use ring::rand::SystemRandom;
+use ring::signature::{EcdsaKeyPair, ECDSA_P256_SHA256_FIXED_SIGNING};
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+use std::io::BufRead;
+use std::path::Path;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+use tokio::sync::mpsc;
+
+/// Lines read ahead of the sender before reading waits for it.
+const READ_AHEAD: usize = 10_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum InputFormat {
+    Ilp,
+    Csv,
+    Ndjson,
+}
+
+impl InputFormat {
+    fn from_str(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "ilp" => Some(InputFormat::Ilp),
+            "csv" => Some(InputFormat::Csv),
+            "ndjson" | "jsonl" => Some(InputFormat::Ndjson),
+            _ => None,
+        }
+    }
+
+    /// Guesses the format from the file extension; stdin is ILP.
+    fn from_path(path: Option<&str>) -> Self {
+        path.and_then(|path| Path::new(path).extension())
+            .and_then(|ext| ext.to_str())
+            .and_then(InputFormat::from_str)
+            .unwrap_or(InputFormat::Ilp)
+    }
+}
+
+/// One ILP line to send, or the reason its input line was rejected.
+struct Record {
+    /// Line (or CSV record) number in the input, for error messages.
+    line: u64,
+    ilp: Result<String>,
+}
+
+#[derive(Default)]
+struct Stats {
+    sent: u64,
+    batches: u64,
+    rejected: u64,
+}
+
+/// Reads the input, sends it in batches and reports every rejected line.
+pub async fn ingest(params: &ConnectionParams, args: &IngestArgs) -> Result<()> {
+    let format = match &args.input_format {
+        Some(format) => InputFormat::from_str(format).ok_or_else(|| anyhow!("unknown input format '{}'", format))?,
+        None => InputFormat::from_path(args.source.as_deref()),
+    };
+    let mut converter = match format {
+        InputFormat::Ilp => None,
+        _ => Some(Converter::new(args)?),
+    };
+    let mut sender = Sender::open(params, args).await?;
+
+    // Reading stdin blocks, so it gets a thread of its own
+    let (tx, mut rx) = mpsc::channel(READ_AHEAD);
+    let source = args.source.clone();
+    let reader = std::thread::spawn(move || read_input(source.as_deref(), format, converter.as_mut(), &tx));
+
+    let mut stats = Stats::default();
+    let mut batch = Vec::new();
+    let mut flush = tokio::time::interval(args.flush_interval);
+    flush.reset();
+    loop {
+        tokio::select! {
+            record = rx.recv() => match record {
+                Some(Record { line, ilp: Ok(ilp) }) => {
+                    batch.push((line, ilp));
+                    if batch.len() as u64 >= args.batch_size {
+                        sender.flush(&mut batch, &mut stats).await?;
+                        flush.reset();
+                    }
+                }
+                Some(Record { line, ilp: Err(e) }) => {
+                    eprintln!("Rejected line {}: {}", line, e);
+                    stats.rejected += 1;
+                }
+                None => break,
+            },
+            _ = flush.tick() => sender.flush(&mut batch, &mut stats).await?,
+        }
+    }
+    sender.flush(&mut batch, &mut stats).await?;
+    sender.close().await?;
+    reader.join().map_err(|_| anyhow!("the input reader panicked"))??;
+
+    eprintln!(
+        "Sent {} line{} in {} batch{} ({} rejected)",
+        stats.sent,
+        if stats.sent == 1 { "" } else { "s" },
+        stats.batches,
+        if stats.batches == 1 { "" } else { "es" },
+        stats.rejected
+    );
+    if stats.rejected > 0 {
+        bail!("{} line{} rejected", stats.rejected, if stats.rejected == 1 { " was" } else { "s were" });
+    }
+    Ok(())
+}
+
+fn read_input(
+    source: Option<&str>,
+    format: InputFormat,
+    mut converter: Option<&mut Converter>,
+    tx: &mpsc::Sender<Record>,
+) -> Result<()> {
+    let input: Box<dyn BufRead + Send> = match source {
+        None | Some("-") => Box::new(std::io::BufReader::new(std::io::stdin())),
+        Some(path) => {
+            let path = shellexpand::tilde(path).into_owned();
+            let file = std::fs::File::open(&path).with_context(|| format!("cannot open '{}'", path))?;
+            Box::new(std::io::BufReader::new(file))
+        }
+    };
+    // the receiver is gone once sending failed; stop reading then
+    let send = |line: u64, ilp: Result<String>| tx.blocking_send(Record { line, ilp }).is_ok();
+
+    match (format, &mut converter) {
+        (InputFormat::Csv, Some(converter)) => {
+            let mut reader = csv::Reader::from_reader(input);
+            let headers = reader.headers()?.clone();
+            for (i, record) in reader.records().enumerate() {
+                let line = record
+                    .as_ref()
+                    .ok()
+                    .and_then(|r| r.position())
+                    .map_or(i as u64 + 2, |p| p.line());
+                let ilp = record
+                    .map_err(anyhow::Error::from)
+                    .and_then(|record| converter.convert_csv(&headers, &record));
+                if !send(line, ilp) {
+                    break;
+                }
+            }
+        }
+        _ => {
+            for (i, line) in input.lines().enumerate() {
+                let line = line?;
+                let trimmed = line.trim();
+                if trimmed.is_empty() || (format == InputFormat::Ilp && trimmed.starts_with('#')) {
+                    continue;
+                }
+                let ilp = match &mut converter {
+                    Some(converter) => converter.convert_json(trimmed),
+                    None => check_line(trimmed).map(|()| trimmed.to_string()),
+                };
+                if !send(i as u64 + 1, ilp) {
+                    break;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Catches lines QuestDB would certainly reject before they are sent. A
+/// line is `table[,symbol=value...] column=value[,...] [timestamp]`.
+fn check_line(line: &str) -> Result<()> {
+    let mut parts = vec![String::new()];
+    let mut escaped = false;
+    let mut quoted = false;
+    for c in line.chars() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '"' => quoted = !quoted,
+            ' ' if !quoted => {
+                parts.push(String::new());
+                continue;
+            }
+            _ => {}
+        }
+        parts.last_mut().unwrap().push(c);
+    }
+    if quoted {
+        bail!("unterminated string value");
+    }
+    if parts[0].is_empty() || parts[0].starts_with(',') {
+        bail!("missing table name");
+    }
+    if parts.len() < 2 || !parts[1].contains('=') {
+        bail!("no column values");
+    }
+    if parts.len() > 3 {
+        bail!("unexpected '{}' after the timestamp", parts[3..].join(" "));
+    }
+    if let Some(timestamp) = parts.get(2) {
+        // an optional unit suffix, as in 1700000000000000t
+        if timestamp.trim_end_matches(|c: char| c.is_ascii_alphabetic()).parse::<i64>().is_err() {
+            bail!("invalid timestamp '{}'", timestamp);
+        }
+    }
+    Ok(())
+}
+
+/// Type of a converted column. It is decided once per column, from the
+/// column's first value unless `--types` names it, because QuestDB creates
+/// the column from the first line and rejects later lines that don't match.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FieldType {
+    Long,
+    Double,
+    Boolean,
+    String,
+}
+
+impl FieldType {
+    fn from_str(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "long" | "int" => Some(FieldType::Long),
+            "double" | "float" => Some(FieldType::Double),
+            "boolean" | "bool" => Some(FieldType::Boolean),
+            "string" | "varchar" => Some(FieldType::String),
+            _ => None,
+        }
+    }
+
+    /// Guesses the type of a CSV column from its first value.
+    fn infer(s: &str) -> Self {
+        // leading zeros, as in 007, are part of an identifier, not a number
+        let digits = s.trim_start_matches(['-', '+']);
+        if digits.len() > 1 && digits.starts_with('0') && !digits.starts_with("0.") {
+            return FieldType::String;
+        }
+        match FieldType::Long.parse(s).or_else(|| FieldType::Double.parse(s)) {
+            Some(Field::Int(_)) => FieldType::Long,
+            Some(_) => FieldType::Double,
+            None if FieldType::Boolean.parse(s).is_some() => FieldType::Boolean,
+            None => FieldType::String,
+        }
+    }
+
+    /// The type of an NDJSON column, from its first value.
+    fn of(field: &Field) -> Self {
+        match field {
+            Field::Bool(_) => FieldType::Boolean,
+            Field::Int(_) => FieldType::Long,
+            Field::Float(_) => FieldType::Double,
+            Field::Text(_) => FieldType::String,
+        }
+    }
+
+    /// Parses a CSV value of this type.
+    fn parse(self, s: &str) -> Option<Field> {
+        match self {
+            FieldType::Long => s.parse().ok().map(Field::Int),
+            // f64 also parses words like "inf" and "nan", which are more likely text
+            FieldType::Double if s.starts_with(|c: char| c.is_ascii_digit() || matches!(c, '-' | '+' | '.')) => {
+                s.parse().ok().filter(|f: &f64| f.is_finite()).map(Field::Float)
+            }
+            FieldType::Double => None,
+            FieldType::Boolean => match s.to_lowercase().as_str() {
+                "true" => Some(Field::Bool(true)),
+                "false" => Some(Field::Bool(false)),
+                _ => None,
+            },
+            FieldType::String => Some(Field::Text(s.to_string())),
+        }
+    }
+
+    /// Converts an NDJSON value to this type; integers widen to doubles.
+    fn coerce(self, field: Field) -> Option<Field> {
+        match (self, field) {
+            (FieldType::Long, field @ Field::Int(_)) => Some(field),
+            (FieldType::Double, Field::Int(i)) => Some(Field::Float(i as f64)),
+            (FieldType::Double, field @ Field::Float(_)) => Some(field),
+            (FieldType::Boolean, field @ Field::Bool(_)) => Some(field),
+            (FieldType::String, field) => Some(Field::Text(field.text())),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for FieldType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FieldType::Long => f.write_str("long"),
+            FieldType::Double => f.write_str("double"),
+            FieldType::Boolean => f.write_str("boolean"),
+            FieldType::String => f.write_str("string"),
+        }
+    }
+}
+
+/// A column value of a converted row.
+#[derive(Debug, Clone, PartialEq)]
+enum Field {
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    Text(String),
+}
+
+impl Field {
+    fn from_json(json: &serde_json::Value) -> Option<Field> {
+        match json {
+            serde_json::Value::Null => None,
+            serde_json::Value::Bool(b) => Some(Field::Bool(*b)),
+            serde_json::Value::Number(n) => Some(match n.as_i64() {
+                Some(i) => Field::Int(i),
+                None => Field::Float(n.as_f64().unwrap_or(f64::NAN)),
+            }),
+            serde_json::Value::String(s) => Some(Field::Text(s.clone())),
+            other => Some(Field::Text(other.to_string())),
+        }
+    }
+
+    fn text(&self) -> String {
+        match self {
+            Field::Bool(b) => b.to_string(),
+            Field::Int(i) => i.to_string(),
+            Field::Float(f) => f.to_string(),
+            Field::Text(s) => s.clone(),
+        }
+    }
+
+    /// The value as an ILP column value.
+    fn ilp(&self) -> String {
+        match self {
+            Field::Bool(b) => if *b { "t" } else { "f" }.to_string(),
+            Field::Int(i) => format!("{}i", i),
+            Field::Float(f) => f.to_string(),
+            Field::Text(s) => format!(
+                "\"{}\"",
+                s.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\\n")
+            ),
+        }
+    }
+
+    /// The value as an ILP timestamp, in nanoseconds.
+    fn timestamp(&self) -> Result<i64> {
+        match self {
+            Field::Int(nanos) => Ok(*nanos),
+            Field::Text(s) => decode::parse_timestamp(s)?
+                .and_utc()
+                .timestamp_nanos_opt()
+                .ok_or_else(|| anyhow!("timestamp '{}' out of range", s)),
+            other => bail!("invalid timestamp '{}'", other.text()),
+        }
+    }
+}
+
+/// Turns CSV records and NDJSON objects into ILP lines for one table.
+struct Converter {
+    table: String,
+    timestamp: Option<String>,
+    symbols: HashSet<String>,
+    /// Column types, from `--types` or decided by each column's first value.
+    types: HashMap<String, FieldType>,
+}
+
+impl Converter {
+    fn new(args: &IngestArgs) -> Result<Self> {
+        let table = match (&args.table, args.source.as_deref()) {
+            (Some(table), _) => table.clone(),
+            (None, Some(source)) if source != "-" => Path::new(source)
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .map(str::to_string)
+                .ok_or_else(|| anyhow!("cannot name a table after '{}'; use --table", source))?,
+            _ => bail!("converting stdin needs --table"),
+        };
+        let mut types = HashMap::new();
+        for entry in &args.types {
+            let column_type = entry
+                .rsplit_once(':')
+                .and_then(|(name, column_type)| Some((name, FieldType::from_str(column_type)?)))
+                .filter(|(name, _)| !name.is_empty());
+            match column_type {
+                Some((name, column_type)) => types.insert(name.to_string(), column_type),
+                None => bail!("invalid --types entry '{}'; expected COLUMN:TYPE with a type of long, double, boolean or string", entry),
+            };
+        }
+        Ok(Self {
+            table,
+            timestamp: args.timestamp.clone(),
+            symbols: args.symbols.iter().cloned().collect(),
+            types,
+        })
+    }
+
+    /// The column's type, deciding it from `first` if it has none yet.
+    fn column_type(&mut self, name: &str, first: impl FnOnce() -> FieldType) -> FieldType {
+        *self.types.entry(name.to_string()).or_insert_with(first)
+    }
+
+    fn convert_csv(&mut self, headers: &csv::StringRecord, record: &csv::StringRecord) -> Result<String> {
+        let mut columns = Vec::new();
+        // empty values are NULL, which ILP expresses by leaving the column out
+        for (name, value) in headers.iter().zip(record.iter()).filter(|(_, value)| !value.is_empty()) {
+            let field = if self.symbols.contains(name) {
+                Field::Text(value.to_string())
+            } else {
+                let column_type = self.column_type(name, || FieldType::infer(value));
+                column_type.parse(value).ok_or_else(|| mismatch(name, column_type, value))?
+            };
+            columns.push((name, field));
+        }
+        self.line(columns.into_iter())
+    }
+
+    fn convert_json(&mut self, line: &str) -> Result<String> {
+        let object: serde_json::Map<String, serde_json::Value> =
+            serde_json::from_str(line).map_err(|e| anyhow!("invalid JSON object: {}", e))?;
+        let mut columns = Vec::new();
+        for (name, value) in &object {
+            let Some(field) = Field::from_json(value) else { continue };
+            let field = if self.symbols.contains(name) {
+                field
+            } else {
+                let column_type = self.column_type(name, || FieldType::of(&field));
+                column_type.coerce(field).ok_or_else(|| mismatch(name, column_type, &value.to_string()))?
+            };
+            columns.push((name.as_str(), field));
+        }
+        self.line(columns.into_iter())
+    }
+
+    fn line<'a>(&self, columns: impl Iterator<Item = (&'a str, Field)>) -> Result<String> {
+        let mut line = escape(&self.table, &[',', ' ']);
+        let mut values = Vec::new();
+        let mut timestamp = None;
+        for (name, field) in columns {
+            if self.timestamp.as_deref() == Some(name) {
+                timestamp = Some(field.timestamp()?);
+            } else if self.symbols.contains(name) {
+                line.push_str(&format!(",{}={}", escape_name(name), escape_name(&field.text())));
+            } else {
+                values.push(format!("{}={}", escape_name(name), field.ilp()));
+            }
+        }
+        if values.is_empty() {
+            bail!("no column values");
+        }
+        line.push(' ');
+        line.push_str(&values.join(","));
+        if let Some(timestamp) = timestamp {
+            line.push_str(&format!(" {}", timestamp));
+        }
+        Ok(line)
+    }
+}
+
+fn mismatch(name: &str, column_type: FieldType, value: &str) -> anyhow::Error {
+    anyhow!("'{}' doesn't fit column '{}' of type {}; set its type with --types", value, name, column_type)
+}
+
+/// Escapes a column or symbol name, or a symbol value.
+fn escape_name(s: &str) -> String {
+    escape(s, &[',', ' ', '='])
+}
+
+fn escape(s: &str, special: &[char]) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        if special.contains(&c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// QuestDB's answer when it rejects an ILP-over-HTTP request.
+#[derive(Debug, Deserialize)]
+struct WriteError {
+    message: String,
+    /// The rejected line within the request, counting from 1.
+    line: Option<usize>,
+}
+
+enum Sender {
+    Http(HttpClient),
+    Tcp(TcpStream),
+}
+
+impl Sender {
+    async fn open(params: &ConnectionParams, args: &IngestArgs) -> Result<Self> {
+        if args.protocol != "tcp" {
+            let client = HttpClient::new(params)?;
+            return Ok(Sender::Http(match &args.token {
+                Some(token) => client.with_token(token.clone()),
+                None => client,
+            }));
+        }
+        if params.tls.enabled {
+            bail!("ILP over TCP with TLS is not supported; use --protocol http");
+        }
+        if args.token.is_some() && args.token_x.is_none() {
+            bail!("authenticating over TCP needs --token, --token-x and --token-y");
+        }
+        let connect = TcpStream::connect((params.host.as_str(), args.tcp_port));
+        let mut stream = match params.connect_timeout {
+            Some(limit) => tokio::time::timeout(limit, connect)
+                .await
+                .map_err(|_| Timeout::new("connecting", limit))?,
+            None => connect.await,
+        }
+        .with_context(|| format!("cannot connect to {}:{}", params.host, args.tcp_port))?;
+        if args.token.is_some() {
+            authenticate(&mut stream, &params.user, args).await?;
+        }
+        Ok(Sender::Tcp(stream))
+    }
+
+    /// Sends the batch and empties it.
+    async fn flush(&mut self, batch: &mut Vec<(u64, String)>, stats: &mut Stats) -> Result<()> {
+        if batch.is_empty() {
+            return Ok(());
+        }
+        match self {
+            Sender::Http(client) => flush_http(client, batch, stats).await?,
+            Sender::Tcp(stream) => {
+                let mut body = String::new();
+                for (_, line) in batch.iter() {
+                    body.push_str(line);
+                    body.push('\n');
+                }
+                // TCP has no replies; a rejected line makes the server hang up
+                if let Err(e) = stream.write_all(body.as_bytes()).await {
+                    bail!(
+                        "the server closed the connection ({}) while lines {}-{} were sent; its log names the rejected line",
+                        e,
+                        batch[0].0,
+                        batch[batch.len() - 1].0
+                    );
+                }
+                stats.sent += batch.len() as u64;
+                stats.batches += 1;
+            }
+        }
+        batch.clear();
+        Ok(())
+    }
+
+    async fn close(self) -> Result<()> {
+        if let Sender::Tcp(mut stream) = self {
+            stream.shutdown().await?;
+        }
+        Ok(())
+    }
+}
+
+/// Sends the batch to `/write`. QuestDB rejects a request as a whole and
+/// names the first bad line, so that line is reported and dropped and the
+/// rest sent again.
+async fn flush_http(client: &HttpClient, batch: &mut Vec<(u64, String)>, stats: &mut Stats) -> Result<()> {
+    while !batch.is_empty() {
+        let mut body = String::new();
+        for (_, line) in batch.iter() {
+            body.push_str(line);
+            body.push('\n');
+        }
+        let request = client.request(reqwest::Method::POST, "/write").body(body);
+        let (status, response) = client.fetch(request).await?;
+        if status.is_success() {
+            stats.sent += batch.len() as u64;
+            stats.batches += 1;
+            return Ok(());
+        }
+        let error: WriteError =
+            serde_json::from_str(&response).map_err(|_| anyhow!("HTTP {}: {}", status, response.trim()))?;
+        // the last line of the message is about the rejected line itself
+        let message = error.message.lines().last().unwrap_or_default();
+        let message = match message.split_once(": ") {
+            Some((prefix, rest)) if prefix.starts_with("error in line") => rest,
+            _ => message,
+        };
+        match error.line.filter(|&n| n >= 1 && n <= batch.len()) {
+            Some(n) => {
+                let (line, _) = batch.remove(n - 1);
+                eprintln!("Rejected line {}: {}", line, message);
+                stats.rejected += 1;
+            }
+            None => bail!("the server rejected lines {}-{}: {}", batch[0].0, batch[batch.len() - 1].0, message),
+        }
+    }
+    Ok(())
+}
+
+/// ILP-over-TCP authentication: send the key id, then sign the challenge
+/// the server answers with. The token is an ECDSA P-256 key in JWK terms.
+async fn authenticate(stream: &mut TcpStream, key_id: &str, args: &IngestArgs) -> Result<()> {
+    let (Some(d), Some(x), Some(y)) = (&args.token, &args.token_x, &args.token_y) else {
+        bail!("authenticating over TCP needs --token, --token-x and --token-y");
+    };
+    let decode = |flag: &str, value: &str| {
+        URL_SAFE_NO_PAD
+            .decode(value.trim_end_matches('='))
+            .with_context(|| format!("{} is not base64url", flag))
+    };
+    let private_key = decode("--token", d)?;
+    let mut public_key = vec![4];
+    public_key.extend(decode("--token-x", x)?);
+    public_key.extend(decode("--token-y", y)?);
+    let rng = SystemRandom::new();
+    let key_pair =
+        EcdsaKeyPair::from_private_key_and_public_key(&ECDSA_P256_SHA256_FIXED_SIGNING, &private_key, &public_key, &rng)
+            .map_err(|e| anyhow!("invalid token: {}", e))?;
+
+    stream.write_all(format!("{}\n", key_id).as_bytes()).await?;
+    let mut challenge = Vec::new();
+    BufReader::new(&mut *stream).read_until(b'\n', &mut challenge).await?;
+    if challenge.pop() != Some(b'\n') {
+        bail!("the server closed the connection during authentication");
+    }
+    let signature = key_pair
+        .sign(&rng, &challenge)
+        .map_err(|_| anyhow!("cannot sign the authentication challenge"))?;
+    stream
+        .write_all(format!("{}\n", STANDARD.encode(signature.as_ref())).as_bytes())
+        .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn converter(timestamp: Option<&str>, symbols: &[&str], types: &[(&str, FieldType)]) -> Converter {
+        Converter {
+            table: "trades".to_string(),
+            timestamp: timestamp.map(str::to_string),
+            symbols: symbols.iter().map(|s| s.to_string()).collect(),
+            types: types.iter().map(|&(name, t)| (name.to_string(), t)).collect(),
+        }
+    }
+
+    fn csv(converter: &mut Converter, rows: &[&[&str]]) -> Vec<Result<String>> {
+        let headers = csv::StringRecord::from(rows[0].to_vec());
+        rows[1..]
+            .iter()
+            .map(|row| converter.convert_csv(&headers, &csv::StringRecord::from(row.to_vec())))
+            .collect()
+    }
+
+    #[test]
+    fn infer_types_from_first_values() {
+        assert_eq!(FieldType::infer("42"), FieldType::Long);
+        assert_eq!(FieldType::infer("-7"), FieldType::Long);
+        assert_eq!(FieldType::infer("2.5"), FieldType::Double);
+        assert_eq!(FieldType::infer("0.5"), FieldType::Double);
+        assert_eq!(FieldType::infer("1e3"), FieldType::Double);
+        assert_eq!(FieldType::infer("TRUE"), FieldType::Boolean);
+        assert_eq!(FieldType::infer("007"), FieldType::String);
+        assert_eq!(FieldType::infer("inf"), FieldType::String);
+        assert_eq!(FieldType::infer("nan"), FieldType::String);
+        assert_eq!(FieldType::infer("abc"), FieldType::String);
+    }
+
+    #[test]
+    fn parse_and_coerce_follow_the_column_type() {
+        assert_eq!(FieldType::Long.parse("1.5"), None);
+        assert_eq!(FieldType::Double.parse("3"), Some(Field::Float(3.0)));
+        assert_eq!(FieldType::Double.parse("infinity"), None);
+        assert_eq!(FieldType::Boolean.parse("yes"), None);
+        assert_eq!(FieldType::String.parse("007"), Some(Field::Text("007".into())));
+
+        assert_eq!(FieldType::Double.coerce(Field::Int(2)), Some(Field::Float(2.0)));
+        assert_eq!(FieldType::Long.coerce(Field::Float(2.0)), None);
+        assert_eq!(FieldType::String.coerce(Field::Int(7)), Some(Field::Text("7".into())));
+        assert_eq!(FieldType::Boolean.coerce(Field::Text("true".into())), None);
+    }
+
+    #[test]
+    fn csv_columns_keep_the_type_of_their_first_value() {
+        let mut converter = converter(None, &["sym"], &[]);
+        let lines = csv(
+            &mut converter,
+            &[&["sym", "qty", "price", "note"], &["a b", "1", "2", "x"], &["c", "", "2.5", "007"], &["d", "1.5", "3", "y"]],
+        );
+        assert_eq!(lines[0].as_ref().unwrap(), "trades,sym=a\\ b qty=1i,price=2i,note=\"x\"");
+        // price became a long from its first value, so 2.5 no longer fits
+        assert!(lines[1].as_ref().unwrap_err().to_string().contains("'2.5' doesn't fit column 'price' of type long"));
+        assert!(lines[2].is_err());
+    }
+
+    #[test]
+    fn csv_types_override_inference() {
+        let mut converter = converter(None, &[], &[("id", FieldType::String), ("price", FieldType::Double)]);
+        let lines = csv(&mut converter, &[&["id", "price"], &["007", "2"], &["8", "2.5"]]);
+        assert_eq!(lines[0].as_ref().unwrap(), "trades id=\"007\",price=2");
+        assert_eq!(lines[1].as_ref().unwrap(), "trades id=\"8\",price=2.5");
+    }
+
+    #[test]
+    fn csv_timestamp_column_becomes_the_line_timestamp() {
+        let mut converter = converter(Some("ts"), &[], &[("ts", FieldType::String)]);
+        let lines = csv(&mut converter, &[&["v", "ts"], &["1", "2024-01-01T00:00:01Z"]]);
+        assert_eq!(lines[0].as_ref().unwrap(), "trades v=1i 1704067201000000000");
+    }
+
+    #[test]
+    fn json_values_widen_but_never_narrow() {
+        let mut converter = converter(None, &["sym"], &[]);
+        assert_eq!(
+            converter.convert_json(r#"{"sym":"a","price":1.5,"ok":true,"skip":null,"note":"say \"hi\""}"#).unwrap(),
+            r#"trades,sym=a price=1.5,ok=t,note="say \"hi\"""#
+        );
+        assert_eq!(converter.convert_json(r#"{"price":2}"#).unwrap(), "trades price=2");
+        assert!(converter.convert_json(r#"{"ok":1}"#).is_err());
+        assert!(converter.convert_json(r#"{"sym":"only"}"#).is_err());
+        assert!(converter.convert_json("[1]").is_err());
+    }
+
+    #[test]
+    fn check_line_catches_malformed_lines() {
+        assert!(check_line("trades,sym=a price=1.5 1700000000000000000").is_ok());
+        assert!(check_line("trades price=1.5 1700000000000000t").is_ok());
+        assert!(check_line(r#"trades note="a b c""#).is_ok());
+        assert!(check_line(r"my\ table price=1").is_ok());
+        assert!(check_line(" price=1").is_err());
+        assert!(check_line("trades").is_err());
+        assert!(check_line("trades price").is_err());
+        assert!(check_line(r#"trades note="open"#).is_err());
+        assert!(check_line("trades price=1 123 extra").is_err());
+        assert!(check_line("trades price=1 yesterday").is_err());
+    }
+}
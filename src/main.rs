@@ -1,57 +1,120 @@
 // src/main.rs
 
 mod cli;
+mod columnar;
 mod commands;
 mod completer;
+mod config;
+mod credentials;
 mod db;
+mod decode;
+mod highlight;
+mod http;
+mod import;
+mod ingest;
+mod markup;
 mod output;
+mod pager;
 mod repl;
+mod session;
+mod sql;
+mod value;
 
 use anyhow::Result;
 use clap::Parser;
 use cli::{Cli, Commands};
-use db::connect_to_db;
+use config::{Config, Profile};
+use db::{Connection, Timeout, Transport};
 use repl::start_repl;
+use session::{Session, Sessions};
 use commands::{execute_script, execute_query_command};
+use output::{JsonLayout, OutputSettings};
+use value::TimestampFormat;
+
+/// Exit status when connecting or a statement runs out of time, as with
+/// timeout(1).
+const EXIT_TIMEOUT: i32 = 124;
+
+/// Exit status when a command fails for any other reason.
+const EXIT_FAILURE: i32 = 1;
 
 #[tokio::main]
 async fn main() -> Result<()> {
     let cli = Cli::parse();
 
-    let host = cli.host.unwrap_or_else(|| "localhost".to_string());
-    let port = cli.port.unwrap_or(8812);
-    let use_tls = cli.use_tls;
+    let config = Config::load(cli.config.as_deref())?;
+    let profile_name = cli.profile.clone().or_else(|| config.default_profile.clone());
+    let url = cli.url_arg.clone().or_else(|| cli.url.clone()).or_else(|| {
+        // an explicitly chosen profile wins over the environment
+        cli.profile.is_none().then(|| std::env::var("QDB_URL").ok()).flatten()
+    });
+
+    // Flags override the URL, which overrides the profile.
+    let mut profile = cli.overrides();
+    if let Some(url) = &url {
+        profile = profile.merge(Profile::from_url(url)?);
+    }
+    if let Some(name) = &profile_name {
+        profile = profile.merge(config.profile(name)?.clone());
+    }
+    if matches!(cli.command, Some(Commands::Import(_) | Commands::Ingest(_))) {
+        // /imp and /write are only served over HTTP
+        profile.transport = Some(Transport::Http);
+    }
+    let mut params = profile.resolve(cli.password_prompt)?;
+    params.connect_timeout = cli.connect_timeout;
 
-    let user = cli.user.as_deref().unwrap_or("admin");
-    let password = cli.password.as_deref().unwrap_or("quest");
-    let dbname = cli.dbname.as_deref().unwrap_or("qdb");
+    // Ingestion may go over TCP and needs no query connection
+    if let Some(Commands::Ingest(args)) = &cli.command {
+        if let Err(e) = ingest::ingest(&params, args).await {
+            eprintln!("Error ingesting: {:#}", e);
+            exit_with_error(&e);
+        }
+        return Ok(());
+    }
+    let history_file = profile.history_file.as_deref().unwrap_or("history.txt");
 
-    match connect_to_db(
-        &host,
-        port,
-        user,
-        password,
-        dbname,
-        use_tls,
-        cli.allow_invalid_cert,
-    )
-    .await
-    {
-        Ok(client) => {
+    let refresh_interval = cli.refresh_interval.as_deref().or(config.refresh_interval.as_deref()).unwrap_or("5m");
+    let refresh_interval = cli::parse_optional_duration(refresh_interval)
+        .map_err(|e| anyhow::anyhow!("invalid refresh interval: {}", e))?;
+
+    let mut settings = OutputSettings::new(profile.format.as_deref().unwrap_or("table"), cli.output.as_deref());
+    settings.json.pretty = !cli.json_compact;
+    settings.json.layout = JsonLayout::from_str(&cli.json_layout).unwrap_or(JsonLayout::Object);
+    settings.timestamps.timezone = TimestampFormat::parse_timezone(&cli.timezone)?;
+    settings.timestamps.set_format(&cli.timestamp_format)?;
+    settings.statement_timeout = cli.statement_timeout;
+
+    match Connection::open(params).await {
+        Ok(connection) => {
             match cli.command {
                 Some(Commands::Exec { sql }) => {
-                    if let Err(e) = execute_query_command(&client, &sql, &cli.format).await {
+                    if let Err(e) = execute_query_command(&connection, &sql, &settings).await {
                         eprintln!("Error executing query: {}", e);
+                        exit_with_error(&e);
                     }
                 }
                 Some(Commands::ExecFrom { source }) => {
-                    if let Err(e) = execute_script(&client, &source, &cli.format).await {
+                    if let Err(e) = execute_script(&connection, &source, &settings).await {
                         eprintln!("Error executing script: {}", e);
+                        exit_with_error(&e);
+                    }
+                }
+                Some(Commands::Import(args)) => {
+                    if let Err(e) = import::import_files(&connection, &args, &settings).await {
+                        eprintln!("Error importing: {:#}", e);
+                        exit_with_error(&e);
                     }
                 }
-                _ => {
-                    println!("Connected to QuestDB at {}:{}.", host, port);
-                    if let Err(e) = start_repl(client, &cli.format, &cli.history_file).await {
+                Some(Commands::Ingest(_)) => unreachable!("ingestion doesn't open a connection"),
+                None => {
+                    println!("Connected to QuestDB at {}.", connection.address());
+                    let session = Session {
+                        name: profile_name.clone().unwrap_or_else(|| "default".to_string()),
+                        profile: profile_name,
+                        connection,
+                    };
+                    if let Err(e) = start_repl(Sessions::new(session), settings, config, history_file, refresh_interval).await {
                         eprintln!("Error in REPL: {}", e);
                     }
                 }
@@ -59,8 +122,17 @@ async fn main() -> Result<()> {
         }
         Err(e) => {
             eprintln!("Failed to connect: {}", e);
+            exit_with_error(&e);
         }
     }
 
     Ok(())
 }
+
+/// Exits after a failed command, with `EXIT_TIMEOUT` if it ran out of time.
+fn exit_with_error(e: &anyhow::Error) -> ! {
+    if e.is::<Timeout>() {
+        std::process::exit(EXIT_TIMEOUT);
+    }
+    std::process::exit(EXIT_FAILURE);
+}
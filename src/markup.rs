@@ -0,0 +1,190 @@
+// src/markup.rs
+
+use crate::output::ResultSink;
+use crate::value::{ColumnMeta, TimestampFormat, Value};
+use anyhow::Result;
+use std::io::Write;
+
+/// GitHub-flavoured Markdown pipe table. Numeric columns are right-aligned.
+pub struct MarkdownSink {
+    out: Box<dyn Write + Send>,
+    timestamps: TimestampFormat,
+}
+
+impl MarkdownSink {
+    pub fn new(out: Box<dyn Write + Send>, timestamps: TimestampFormat) -> Self {
+        Self { out, timestamps }
+    }
+}
+
+fn escape_markdown(s: &str) -> String {
+    s.replace('\\', "\\\\")
+        .replace('|', "\\|")
+        .replace("\r\n", "<br>")
+        .replace('\n', "<br>")
+}
+
+impl ResultSink for MarkdownSink {
+    fn begin(&mut self, columns: &[ColumnMeta]) -> Result<()> {
+        if columns.is_empty() {
+            return Ok(());
+        }
+        let headers: Vec<String> = columns.iter().map(|col| escape_markdown(&col.name)).collect();
+        let alignments: Vec<&str> = columns
+            .iter()
+            .map(|col| if col.column_type.is_numeric() { "---:" } else { ":---" })
+            .collect();
+        writeln!(self.out, "| {} |", headers.join(" | "))?;
+        writeln!(self.out, "| {} |", alignments.join(" | "))?;
+        Ok(())
+    }
+
+    fn row(&mut self, values: &[Value]) -> Result<()> {
+        let cells: Vec<String> = values.iter().map(|v| escape_markdown(&v.render(&self.timestamps))).collect();
+        writeln!(self.out, "| {} |", cells.join(" | "))?;
+        Ok(())
+    }
+
+    fn end(&mut self) -> Result<()> {
+        self.out.flush()?;
+        Ok(())
+    }
+}
+
+/// HTML `<table>` with a `<thead>` and escaped cells.
+pub struct HtmlSink {
+    out: Box<dyn Write + Send>,
+    timestamps: TimestampFormat,
+    numeric: Vec<bool>,
+}
+
+impl HtmlSink {
+    pub fn new(out: Box<dyn Write + Send>, timestamps: TimestampFormat) -> Self {
+        Self { out, timestamps, numeric: Vec::new() }
+    }
+}
+
+fn escape_html(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+impl ResultSink for HtmlSink {
+    fn begin(&mut self, columns: &[ColumnMeta]) -> Result<()> {
+        if columns.is_empty() {
+            return Ok(());
+        }
+        self.numeric = columns.iter().map(|col| col.column_type.is_numeric()).collect();
+        writeln!(self.out, "<table>")?;
+        writeln!(self.out, "  <thead>")?;
+        writeln!(self.out, "    <tr>")?;
+        for col in columns {
+            writeln!(self.out, "      <th>{}</th>", escape_html(&col.name))?;
+        }
+        writeln!(self.out, "    </tr>")?;
+        writeln!(self.out, "  </thead>")?;
+        writeln!(self.out, "  <tbody>")?;
+        Ok(())
+    }
+
+    fn row(&mut self, values: &[Value]) -> Result<()> {
+        writeln!(self.out, "    <tr>")?;
+        for (i, value) in values.iter().enumerate() {
+            let align = if self.numeric.get(i).copied().unwrap_or(false) {
+                " style=\"text-align: right\""
+            } else {
+                ""
+            };
+            writeln!(self.out, "      <td{}>{}</td>", align, escape_html(&value.render(&self.timestamps)))?;
+        }
+        writeln!(self.out, "    </tr>")?;
+        Ok(())
+    }
+
+    fn end(&mut self) -> Result<()> {
+        if !self.numeric.is_empty() {
+            writeln!(self.out, "  </tbody>")?;
+            writeln!(self.out, "</table>")?;
+        }
+        self.out.flush()?;
+        Ok(())
+    }
+}
+
+/// LaTeX `tabular` environment with `\hline` rules.
+pub struct LatexSink {
+    out: Box<dyn Write + Send>,
+    timestamps: TimestampFormat,
+    columns: usize,
+}
+
+impl LatexSink {
+    pub fn new(out: Box<dyn Write + Send>, timestamps: TimestampFormat) -> Self {
+        Self { out, timestamps, columns: 0 }
+    }
+}
+
+fn escape_latex(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '\\' => escaped.push_str("\\textbackslash{}"),
+            '~' => escaped.push_str("\\textasciitilde{}"),
+            '^' => escaped.push_str("\\textasciicircum{}"),
+            '<' => escaped.push_str("\\textless{}"),
+            '>' => escaped.push_str("\\textgreater{}"),
+            '|' => escaped.push_str("\\textbar{}"),
+            '&' | '%' | '$' | '#' | '_' | '{' | '}' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            '\n' => escaped.push(' '),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+impl ResultSink for LatexSink {
+    fn begin(&mut self, columns: &[ColumnMeta]) -> Result<()> {
+        if columns.is_empty() {
+            return Ok(());
+        }
+        self.columns = columns.len();
+        let spec: String = columns
+            .iter()
+            .map(|col| if col.column_type.is_numeric() { 'r' } else { 'l' })
+            .collect();
+        let headers: Vec<String> = columns.iter().map(|col| escape_latex(&col.name)).collect();
+        writeln!(self.out, "\\begin{{tabular}}{{{}}}", spec)?;
+        writeln!(self.out, "\\hline")?;
+        writeln!(self.out, "{} \\\\", headers.join(" & "))?;
+        writeln!(self.out, "\\hline")?;
+        Ok(())
+    }
+
+    fn row(&mut self, values: &[Value]) -> Result<()> {
+        let cells: Vec<String> = values.iter().map(|v| escape_latex(&v.render(&self.timestamps))).collect();
+        writeln!(self.out, "{} \\\\", cells.join(" & "))?;
+        Ok(())
+    }
+
+    fn end(&mut self) -> Result<()> {
+        if self.columns > 0 {
+            writeln!(self.out, "\\hline")?;
+            writeln!(self.out, "\\end{{tabular}}")?;
+        }
+        self.out.flush()?;
+        Ok(())
+    }
+}
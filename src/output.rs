@@ -1,12 +1,24 @@
 // src/output.rs
 
-use tokio_postgres::{Row, types::Type, Column};
 use serde::Serialize;
-use prettytable::{Table, Row as PrettyRow, Cell};
 use csv::WriterBuilder;
-use anyhow::Result;
-use chrono::NaiveDateTime;
+use anyhow::{anyhow, Result};
+use std::io::Write;
+use crate::columnar::{ColumnarFormat, ColumnarSink};
+use crate::markup::{HtmlSink, LatexSink, MarkdownSink};
+use crate::pager::{terminal_dimensions, PagerMode, PagerWriter};
+use crate::value::{ColumnMeta, TimestampFormat, Value};
+use std::io::IsTerminal;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
+/// Number of rows the table format buffers to size its columns before it
+/// starts streaming. Rows after that are printed using the same widths,
+/// except that without a width limit a wider cell widens its column from
+/// that row on, below a new separator line.
+const TABLE_BUFFER_ROWS: usize = 1000;
+
+/// A row in the columns+values JSON layout.
 #[derive(Serialize)]
 struct JsonRow {
     columns: Vec<String>,
@@ -17,8 +29,15 @@ pub enum OutputFormat {
     Table,
     Csv,
     Json,
+    Ndjson,
     Vertical,
     Record,
+    Markdown,
+    Html,
+    Latex,
+    Parquet,
+    ArrowStream,
+    ArrowFile,
 }
 
 impl OutputFormat {
@@ -27,200 +46,836 @@ impl OutputFormat {
             "table" => OutputFormat::Table,
             "csv" => OutputFormat::Csv,
             "json" => OutputFormat::Json,
+            "ndjson" | "jsonl" => OutputFormat::Ndjson,
             "vertical" => OutputFormat::Vertical,
             "record" => OutputFormat::Record,
+            "markdown" | "md" => OutputFormat::Markdown,
+            "html" => OutputFormat::Html,
+            "latex" | "tex" => OutputFormat::Latex,
+            "parquet" => OutputFormat::Parquet,
+            "arrow" | "arrow-stream" => OutputFormat::ArrowStream,
+            "arrow-file" | "feather" => OutputFormat::ArrowFile,
             _ => OutputFormat::Table,
         }
     }
+
+    /// Creates a sink that renders results in this format to `out`.
+    /// `to_terminal` tells whether `out` ends up on the user's screen.
+    pub fn sink(
+        &self,
+        out: Box<dyn Write + Send>,
+        settings: &OutputSettings,
+        to_terminal: bool,
+    ) -> Box<dyn ResultSink + Send> {
+        let ts = settings.timestamps.clone();
+        match self {
+            OutputFormat::Table => {
+                Box::new(TableSink::new(out, ts, settings.table_width(to_terminal), settings.wrap))
+            }
+            OutputFormat::Csv => Box::new(CsvSink::new(out, ts)),
+            OutputFormat::Json => Box::new(JsonSink::new(out, ts, settings.json, false)),
+            OutputFormat::Ndjson => Box::new(JsonSink::new(out, ts, settings.json, true)),
+            OutputFormat::Vertical => Box::new(VerticalSink::new(out, ts)),
+            OutputFormat::Record => Box::new(RecordSink::new(out, ts)),
+            OutputFormat::Markdown => Box::new(MarkdownSink::new(out, ts)),
+            OutputFormat::Html => Box::new(HtmlSink::new(out, ts)),
+            OutputFormat::Latex => Box::new(LatexSink::new(out, ts)),
+            OutputFormat::Parquet => Box::new(ColumnarSink::new(out, ts, ColumnarFormat::Parquet)),
+            OutputFormat::ArrowStream => Box::new(ColumnarSink::new(out, ts, ColumnarFormat::ArrowStream)),
+            OutputFormat::ArrowFile => Box::new(ColumnarSink::new(out, ts, ColumnarFormat::ArrowFile)),
+        }
+    }
+
+    /// Whether the format produces binary data rather than text.
+    pub fn is_binary(&self) -> bool {
+        matches!(
+            self,
+            OutputFormat::Parquet | OutputFormat::ArrowStream | OutputFormat::ArrowFile
+        )
+    }
 }
 
-pub async fn print_query_results(rows: Vec<Row>, format: &str) -> Result<()> {
-    let output_format = OutputFormat::from_str(format);
+/// Shape of each row in `json` and `ndjson` output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JsonLayout {
+    /// `{"column": value, ...}`
+    Object,
+    /// `{"columns": [...], "values": [...]}`
+    Envelope,
+}
 
-    match output_format {
-        OutputFormat::Table => print_table(&rows).await?,
-        OutputFormat::Csv => print_csv(&rows).await?,
-        OutputFormat::Json => print_json(&rows).await?,
-        OutputFormat::Vertical => print_vertical(&rows).await?,
-        OutputFormat::Record => print_record(&rows).await?,
+impl JsonLayout {
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "object" => Some(JsonLayout::Object),
+            "envelope" => Some(JsonLayout::Envelope),
+            _ => None,
+        }
     }
+}
 
-    Ok(())
+impl std::fmt::Display for JsonLayout {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            JsonLayout::Object => f.write_str("object"),
+            JsonLayout::Envelope => f.write_str("envelope"),
+        }
+    }
+}
+
+/// Options for `json` and `ndjson` output. `ndjson` is always compact.
+#[derive(Debug, Clone, Copy)]
+pub struct JsonOptions {
+    pub pretty: bool,
+    pub layout: JsonLayout,
 }
 
-async fn print_table(rows: &[Row]) -> Result<()> {
-    if rows.is_empty() {
-        println!("(No rows returned)");
-        return Ok(());
+impl Default for JsonOptions {
+    fn default() -> Self {
+        Self { pretty: true, layout: JsonLayout::Object }
     }
+}
 
-    let columns = rows[0].columns();
-    let headers: Vec<&str> = columns.iter().map(|col| col.name()).collect();
+/// Width that `table` output is laid out for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TableWidth {
+    /// The terminal width when printing to a terminal, unlimited otherwise.
+    Auto,
+    /// Size columns to their content.
+    Unlimited,
+    Columns(usize),
+}
 
-    let mut table = Table::new();
-    table.add_row(PrettyRow::new(
-        headers.iter().map(|h| Cell::new(h)).collect(),
-    ));
+impl TableWidth {
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "auto" => Some(TableWidth::Auto),
+            "off" | "unlimited" => Some(TableWidth::Unlimited),
+            n => n.parse().ok().filter(|&n| n > 0).map(TableWidth::Columns),
+        }
+    }
+}
 
-    for row in rows {
-        let mut table_row = PrettyRow::new(Vec::new());
-        for (i, column) in row.columns().iter().enumerate() {
-            let value = get_column_value(row, column, i).await?;
-            table_row.add_cell(Cell::new(&value));
+impl std::fmt::Display for TableWidth {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TableWidth::Auto => f.write_str("auto"),
+            TableWidth::Unlimited => f.write_str("off"),
+            TableWidth::Columns(n) => write!(f, "{}", n),
         }
-        table.add_row(table_row);
     }
+}
 
-    table.printstd();
-    Ok(())
+/// How query results are rendered and where they are written.
+#[derive(Clone)]
+pub struct OutputSettings {
+    pub format: String,
+    /// File to write results to instead of stdout.
+    pub output_file: Option<String>,
+    pub json: JsonOptions,
+    /// Whether terminal output goes through a pager.
+    pub pager: PagerMode,
+    pub width: TableWidth,
+    /// Wrap long table cells instead of truncating them.
+    pub wrap: bool,
+    pub timestamps: TimestampFormat,
+    /// Cancel statements that keep us waiting on the server longer than
+    /// this in total; time spent writing their rows doesn't count.
+    pub statement_timeout: Option<Duration>,
+    /// The output file once the first result has been written to it. Shared
+    /// by clones so every statement of an invocation appends to one file.
+    opened: Arc<Mutex<Option<OpenedFile>>>,
+}
+
+struct OpenedFile {
+    path: String,
+    file: std::fs::File,
 }
 
-async fn print_csv(rows: &[Row]) -> Result<()> {
-    if rows.is_empty() {
-        println!("(No rows returned)");
-        return Ok(());
+impl OutputSettings {
+    pub fn new(format: &str, output_file: Option<&str>) -> Self {
+        Self {
+            format: format.to_string(),
+            output_file: output_file.map(str::to_string),
+            json: JsonOptions::default(),
+            pager: PagerMode::Off,
+            width: TableWidth::Auto,
+            wrap: false,
+            timestamps: TimestampFormat::default(),
+            statement_timeout: None,
+            opened: Arc::default(),
+        }
     }
 
-    let columns = rows[0].columns();
-    let headers: Vec<&str> = columns.iter().map(|col| col.name()).collect();
+    /// Maximum table width in characters, or `None` for no limit.
+    pub fn table_width(&self, to_terminal: bool) -> Option<usize> {
+        match self.width {
+            TableWidth::Columns(n) => Some(n),
+            TableWidth::Unlimited => None,
+            TableWidth::Auto if to_terminal => terminal_dimensions().map(|(width, _)| width),
+            TableWidth::Auto => None,
+        }
+    }
 
-    let mut wtr = WriterBuilder::new()
-        .has_headers(true)
-        .from_writer(std::io::stdout());
+    /// Creates a sink for the current format, writing to the output file if
+    /// one is set and to stdout otherwise.
+    ///
+    /// The output file is created by the first result and later results are
+    /// appended to it. Parquet and Arrow files hold a single result, so a
+    /// second one is refused rather than written as a corrupt file.
+    pub fn sink(&self) -> Result<Box<dyn ResultSink + Send>> {
+        let format = OutputFormat::from_str(&self.format);
+        let to_terminal = self.output_file.is_none() && std::io::stdout().is_terminal();
+        let out: Box<dyn Write + Send> = match &self.output_file {
+            Some(path) => {
+                let path = shellexpand::tilde(path).to_string();
+                let mut opened = self.opened.lock().unwrap();
+                let file = match opened.as_ref() {
+                    Some(open) if open.path == path && format.is_binary() => {
+                        return Err(anyhow!(
+                            "'{}' output holds a single result and {} already has one",
+                            self.format,
+                            path
+                        ));
+                    }
+                    Some(open) if open.path == path => open.file.try_clone()?,
+                    _ => {
+                        let file = std::fs::File::create(&path)?;
+                        *opened = Some(OpenedFile { path, file: file.try_clone()? });
+                        file
+                    }
+                };
+                Box::new(std::io::BufWriter::new(file))
+            }
+            None if format.is_binary() && to_terminal => {
+                return Err(anyhow!(
+                    "'{}' output is binary; use --output <file> or redirect stdout",
+                    self.format
+                ));
+            }
+            None if self.pager != PagerMode::Off && to_terminal => {
+                Box::new(PagerWriter::new(self.pager))
+            }
+            None => Box::new(std::io::stdout()),
+        };
+        Ok(format.sink(out, self, to_terminal))
+    }
+}
 
-    wtr.write_record(&headers)?;
+/// Whether an output error means the reader went away, e.g. the user quit
+/// the pager or output was piped into `head`.
+pub fn is_broken_pipe(err: &anyhow::Error) -> bool {
+    let io_error = match err.downcast_ref::<csv::Error>() {
+        Some(e) => match e.kind() {
+            csv::ErrorKind::Io(e) => Some(e),
+            _ => None,
+        },
+        None => err.downcast_ref::<std::io::Error>(),
+    };
+    io_error.is_some_and(|e| e.kind() == std::io::ErrorKind::BrokenPipe)
+}
 
-    for row in rows {
-        let mut record = Vec::new();
-        for (i, column) in row.columns().iter().enumerate() {
-            let value = get_column_value(row, column, i).await?;
-            record.push(value);
+/// Receives a query result as it streams in from the server.
+///
+/// `begin` is called once with the result columns, `row` once per row and
+/// `end` once after the last row, so implementations can print headers and
+/// footers exactly once without holding the whole result in memory.
+pub trait ResultSink {
+    fn begin(&mut self, columns: &[ColumnMeta]) -> Result<()>;
+    fn row(&mut self, values: &[Value]) -> Result<()>;
+    fn end(&mut self) -> Result<()>;
+}
+
+struct TableSink {
+    out: Box<dyn Write + Send>,
+    timestamps: TimestampFormat,
+    headers: Vec<String>,
+    widths: Vec<usize>,
+    buffered: Vec<Vec<String>>,
+    streaming: bool,
+    rows: usize,
+    /// Maximum line width, or `None` to size columns to their content.
+    max_width: Option<usize>,
+    /// Wrap long cells onto several lines instead of truncating them.
+    wrap: bool,
+    /// The table didn't fit even with shrunk columns; print rows vertically.
+    vertical: bool,
+}
+
+impl TableSink {
+    fn new(out: Box<dyn Write + Send>, timestamps: TimestampFormat, max_width: Option<usize>, wrap: bool) -> Self {
+        Self {
+            out,
+            timestamps,
+            headers: Vec::new(),
+            widths: Vec::new(),
+            buffered: Vec::new(),
+            streaming: false,
+            rows: 0,
+            max_width,
+            wrap,
+            vertical: false,
         }
-        wtr.write_record(&record)?;
     }
 
-    wtr.flush()?;
-    Ok(())
+    fn print_separator(&mut self) -> Result<()> {
+        let line: String = self
+            .widths
+            .iter()
+            .map(|w| format!("+{}", "-".repeat(w + 2)))
+            .collect();
+        writeln!(self.out, "{}+", line)?;
+        Ok(())
+    }
+
+    fn print_cells(&mut self, cells: &[String]) -> Result<()> {
+        let limited = self.max_width.is_some();
+        let lines: Vec<Vec<String>> = cells
+            .iter()
+            .zip(&self.widths)
+            .map(|(cell, &width)| layout_cell(cell, width, limited, self.wrap))
+            .collect();
+        let height = lines.iter().map(Vec::len).max().unwrap_or(1);
+        for n in 0..height {
+            let mut line = String::new();
+            for (cell_lines, width) in lines.iter().zip(&self.widths) {
+                let text = cell_lines.get(n).map(String::as_str).unwrap_or("");
+                let pad = width.saturating_sub(text.chars().count());
+                line.push_str(&format!("| {}{} ", text, " ".repeat(pad)));
+            }
+            writeln!(self.out, "{}|", line)?;
+        }
+        Ok(())
+    }
+
+    /// Widens the columns to fit `cells`. Returns whether any column grew.
+    fn widen(&mut self, cells: &[String]) -> bool {
+        let mut grown = false;
+        for (width, cell) in self.widths.iter_mut().zip(cells) {
+            let needed = cell_width(cell);
+            if needed > *width {
+                *width = needed;
+                grown = true;
+            }
+        }
+        grown
+    }
+
+    /// Sizes the columns from the buffered rows, prints the header and the
+    /// buffered rows, then switches to streaming.
+    fn flush_buffer(&mut self) -> Result<()> {
+        for row in &self.buffered {
+            for (width, cell) in self.widths.iter_mut().zip(row) {
+                *width = (*width).max(cell_width(cell));
+            }
+        }
+        if let Some(max_width) = self.max_width {
+            self.vertical = !fit_widths(&mut self.widths, max_width);
+        }
+        self.streaming = true;
+
+        let buffered = std::mem::take(&mut self.buffered);
+        if self.vertical {
+            for (n, row) in buffered.iter().enumerate() {
+                write_vertical_row(&mut self.out, &self.headers, n + 1, row)?;
+            }
+            return Ok(());
+        }
+        let headers = self.headers.clone();
+        self.print_separator()?;
+        self.print_cells(&headers)?;
+        self.print_separator()?;
+        for row in buffered {
+            self.print_cells(&row)?;
+        }
+        Ok(())
+    }
+}
+
+/// Columns are never shrunk below this many characters; if the table still
+/// doesn't fit, it is printed vertically instead.
+const MIN_COLUMN_WIDTH: usize = 8;
+
+/// Display width of a cell: its longest line.
+fn cell_width(cell: &str) -> usize {
+    cell.lines().map(|line| line.chars().count()).max().unwrap_or(0)
+}
+
+/// Shrinks the widest columns until the table fits in `max_width`
+/// characters. Returns false if it can't fit even at the minimum widths.
+fn fit_widths(widths: &mut [usize], max_width: usize) -> bool {
+    let budget = match max_width.checked_sub(3 * widths.len() + 1) {
+        Some(budget) => budget,
+        None => return false,
+    };
+    if widths.iter().sum::<usize>() <= budget {
+        return true;
+    }
+    let floor = |w: usize| w.min(MIN_COLUMN_WIDTH);
+    let total = |cap: usize| -> usize { widths.iter().map(|&w| w.min(cap).max(floor(w))).sum() };
+    if total(0) > budget {
+        return false;
+    }
+    // Largest per-column cap that still fits.
+    let (mut low, mut high) = (0, widths.iter().copied().max().unwrap_or(0));
+    while low < high {
+        let mid = (low + high).div_ceil(2);
+        if total(mid) <= budget {
+            low = mid;
+        } else {
+            high = mid - 1;
+        }
+    }
+    for w in widths.iter_mut() {
+        *w = (*w).min(low).max(floor(*w));
+    }
+    true
+}
+
+/// Splits a cell into the lines it occupies in a column of `width`.
+///
+/// Unless `limited`, cells are printed as they are. Otherwise they are either
+/// wrapped onto several lines or cut short with an ellipsis.
+fn layout_cell(cell: &str, width: usize, limited: bool, wrap: bool) -> Vec<String> {
+    if !limited {
+        return cell.split('\n').map(str::to_string).collect();
+    }
+    if wrap {
+        let mut lines = Vec::new();
+        for line in cell.split('\n') {
+            let chars: Vec<char> = line.chars().collect();
+            if chars.is_empty() {
+                lines.push(String::new());
+            }
+            for chunk in chars.chunks(width.max(1)) {
+                lines.push(chunk.iter().collect());
+            }
+        }
+        return lines;
+    }
+    let flat = cell.replace('\n', " ");
+    if flat.chars().count() <= width {
+        return vec![flat];
+    }
+    let mut truncated: String = flat.chars().take(width.saturating_sub(1)).collect();
+    truncated.push('…');
+    vec![truncated]
 }
 
-async fn print_json(rows: &[Row]) -> Result<()> {
-    if rows.is_empty() {
-        println!("[]");
-        return Ok(());
-    }
-
-    let columns = rows[0].columns();
-    let mut json_rows = Vec::new();
-
-    for row in rows {
-        let mut json_row = serde_json::Map::new();
-        for (i, col) in columns.iter().enumerate() {
-            let value = match *col.type_() {
-                Type::TIMESTAMP | Type::TIMESTAMPTZ => {
-                    row.try_get::<_, Option<NaiveDateTime>>(i)?
-                        .map(|v| serde_json::Value::String(v.format("%Y-%m-%d %H:%M:%S").to_string()))
-                        .unwrap_or(serde_json::Value::Null)
-                },
-                Type::FLOAT8 => {
-                    row.try_get::<_, Option<f64>>(i)?
-                        .map(serde_json::Value::from)
-                        .unwrap_or(serde_json::Value::Null)
-                },
-                Type::VARCHAR => {
-                    row.try_get::<_, Option<String>>(i)?
-                        .map(serde_json::Value::from)
-                        .unwrap_or(serde_json::Value::Null)
-                },
-                Type::BOOL => {
-                    row.try_get::<_, Option<bool>>(i)?
-                        .map(serde_json::Value::from)
-                        .unwrap_or(serde_json::Value::Null)
-                },
-                Type::INT4 => {
-                    row.try_get::<_, Option<i32>>(i)?
-                        .map(serde_json::Value::from)
-                        .unwrap_or(serde_json::Value::Null)
-                },
-                _ => serde_json::Value::String("Unsupported Type".to_string()),
-            };
-            json_row.insert(col.name().to_string(), value);
-        }
-        json_rows.push(serde_json::Value::Object(json_row));
-    }
-
-    let serialized = serde_json::to_string_pretty(&json_rows)?;
-    println!("{}", serialized);
+fn write_vertical_row(out: &mut dyn Write, names: &[String], row: usize, cells: &[String]) -> Result<()> {
+    writeln!(out, "Row {}:", row)?;
+    for (name, cell) in names.iter().zip(cells) {
+        writeln!(out, "  {}: {}", name, cell)?;
+    }
+    writeln!(out)?;
     Ok(())
 }
 
-async fn print_vertical(rows: &[Row]) -> Result<()> {
-    if rows.is_empty() {
-        println!("(No rows returned)");
-        return Ok(());
+impl ResultSink for TableSink {
+    fn begin(&mut self, columns: &[ColumnMeta]) -> Result<()> {
+        self.headers = columns.iter().map(|col| col.name.clone()).collect();
+        self.widths = self.headers.iter().map(|h| h.chars().count()).collect();
+        Ok(())
     }
 
-    let columns = rows[0].columns();
+    fn row(&mut self, values: &[Value]) -> Result<()> {
+        let values: Vec<String> = values.iter().map(|v| v.render(&self.timestamps)).collect();
+        self.rows += 1;
+        if !self.streaming {
+            self.buffered.push(values);
+            if self.buffered.len() >= TABLE_BUFFER_ROWS {
+                self.flush_buffer()?;
+            }
+        } else if self.vertical {
+            write_vertical_row(&mut self.out, &self.headers, self.rows, &values)?;
+        } else {
+            if self.max_width.is_none() && self.widen(&values) {
+                self.print_separator()?;
+            }
+            self.print_cells(&values)?;
+        }
+        Ok(())
+    }
 
-    for (row_num, row) in rows.iter().enumerate() {
-        println!("Row {}:", row_num + 1);
-        for (i, col) in columns.iter().enumerate() {
-            let value = get_column_value(row, col, i).await?;
-            println!("  {}: {}", col.name(), value);
+    fn end(&mut self) -> Result<()> {
+        if self.widths.is_empty() {
+            return Ok(());
+        }
+        if self.rows == 0 {
+            writeln!(self.out, "(No rows returned)")?;
+            return Ok(());
+        }
+        if !self.streaming {
+            self.flush_buffer()?;
+        }
+        if !self.vertical {
+            self.print_separator()?;
         }
-        println!();
+        self.out.flush()?;
+        Ok(())
     }
+}
 
-    Ok(())
+struct CsvSink {
+    wtr: csv::Writer<Box<dyn Write + Send>>,
+    timestamps: TimestampFormat,
+}
+
+impl CsvSink {
+    fn new(out: Box<dyn Write + Send>, timestamps: TimestampFormat) -> Self {
+        Self {
+            wtr: WriterBuilder::new().has_headers(true).from_writer(out),
+            timestamps,
+        }
+    }
+}
+
+impl ResultSink for CsvSink {
+    fn begin(&mut self, columns: &[ColumnMeta]) -> Result<()> {
+        if !columns.is_empty() {
+            self.wtr.write_record(columns.iter().map(|col| col.name.as_str()))?;
+        }
+        Ok(())
+    }
+
+    fn row(&mut self, values: &[Value]) -> Result<()> {
+        self.wtr.write_record(values.iter().map(|v| v.render(&self.timestamps)))?;
+        Ok(())
+    }
+
+    fn end(&mut self) -> Result<()> {
+        self.wtr.flush()?;
+        Ok(())
+    }
+}
+
+/// Streams rows as a JSON array (`json`) or one line per row (`ndjson`).
+struct JsonSink {
+    out: Box<dyn Write + Send>,
+    timestamps: TimestampFormat,
+    columns: Vec<ColumnMeta>,
+    /// Object keys, one per column, in column order and without duplicates.
+    keys: Vec<String>,
+    options: JsonOptions,
+    lines: bool,
+    rows: usize,
 }
 
-async fn print_record(rows: &[Row]) -> Result<()> {
-    if rows.is_empty() {
-        println!("(No rows returned)");
-        return Ok(());
+impl JsonSink {
+    fn new(out: Box<dyn Write + Send>, timestamps: TimestampFormat, options: JsonOptions, lines: bool) -> Self {
+        Self { out, timestamps, columns: Vec::new(), keys: Vec::new(), options, lines, rows: 0 }
     }
 
-    let columns = rows[0].columns();
+    fn render(&self, values: &[Value]) -> Result<serde_json::Value> {
+        Ok(match self.options.layout {
+            JsonLayout::Object => json_object(&self.keys, values, &self.timestamps),
+            JsonLayout::Envelope => serde_json::to_value(JsonRow {
+                columns: self.columns.iter().map(|col| col.name.clone()).collect(),
+                values: values
+                    .iter()
+                    .map(|v| match v {
+                        Value::Null => None,
+                        v => Some(v.to_json(&self.timestamps)),
+                    })
+                    .collect(),
+            })?,
+        })
+    }
+}
 
-    for row in rows {
-        let mut record = Vec::new();
-        for (i, column) in columns.iter().enumerate() {
-            let value = get_column_value(row, column, i).await?;
-            record.push(format!("{}: {}", column.name(), value));
+impl ResultSink for JsonSink {
+    fn begin(&mut self, columns: &[ColumnMeta]) -> Result<()> {
+        self.columns = columns.to_vec();
+        self.keys = unique_keys(columns);
+        Ok(())
+    }
+
+    fn row(&mut self, values: &[Value]) -> Result<()> {
+        let json = self.render(values)?;
+        if self.lines {
+            writeln!(self.out, "{}", serde_json::to_string(&json)?)?;
+        } else if self.options.pretty {
+            let serialized = serde_json::to_string_pretty(&json)?;
+            write!(self.out, "{}", if self.rows == 0 { "[\n" } else { ",\n" })?;
+            let indented: Vec<String> = serialized.lines().map(|line| format!("  {}", line)).collect();
+            write!(self.out, "{}", indented.join("\n"))?;
+        } else {
+            write!(self.out, "{}", if self.rows == 0 { "[" } else { "," })?;
+            write!(self.out, "{}", serde_json::to_string(&json)?)?;
         }
-        println!("{}", record.join(", "));
+        self.rows += 1;
+        Ok(())
     }
 
-    Ok(())
+    fn end(&mut self) -> Result<()> {
+        if !self.lines {
+            if self.rows > 0 {
+                writeln!(self.out, "{}]", if self.options.pretty { "\n" } else { "" })?;
+            } else if !self.columns.is_empty() {
+                writeln!(self.out, "[]")?;
+            }
+        }
+        self.out.flush()?;
+        Ok(())
+    }
 }
 
-// Helper function to retrieve and format column values
-async fn get_column_value(row: &Row, column: &Column, i: usize) -> Result<String> {
-    let value = match *column.type_() {
-        Type::TIMESTAMP | Type::TIMESTAMPTZ => {
-            row.try_get::<_, Option<NaiveDateTime>>(i)?
-                .map(|v| v.format("%Y-%m-%dT%H:%M:%S%.6fZ").to_string())
-                .unwrap_or_else(|| "NULL".to_string())
-        },
-        Type::FLOAT8 => {
-            row.try_get::<_, Option<f64>>(i)?
-                .map(|v| v.to_string())
-                .unwrap_or_else(|| "NULL".to_string())
-        },
-        Type::VARCHAR => {
-            row.try_get::<_, Option<String>>(i)?
-                .unwrap_or_else(|| "NULL".to_string())
-        },
-        Type::BOOL => {
-            row.try_get::<_, Option<bool>>(i)?
-                .map(|v| v.to_string())
-                .unwrap_or_else(|| "NULL".to_string())
-        },
-        Type::INT4 => {
-            row.try_get::<_, Option<i32>>(i)?
-                .map(|v| v.to_string())
-                .unwrap_or_else(|| "NULL".to_string())
-        },
-        _ => "Unsupported Type".to_string(),
-    };
-    Ok(value)
+struct VerticalSink {
+    out: Box<dyn Write + Send>,
+    timestamps: TimestampFormat,
+    names: Vec<String>,
+    rows: usize,
+}
+
+impl VerticalSink {
+    fn new(out: Box<dyn Write + Send>, timestamps: TimestampFormat) -> Self {
+        Self { out, timestamps, names: Vec::new(), rows: 0 }
+    }
+}
+
+impl ResultSink for VerticalSink {
+    fn begin(&mut self, columns: &[ColumnMeta]) -> Result<()> {
+        self.names = columns.iter().map(|col| col.name.clone()).collect();
+        Ok(())
+    }
+
+    fn row(&mut self, values: &[Value]) -> Result<()> {
+        self.rows += 1;
+        let cells: Vec<String> = values.iter().map(|v| v.render(&self.timestamps)).collect();
+        write_vertical_row(&mut self.out, &self.names, self.rows, &cells)
+    }
+
+    fn end(&mut self) -> Result<()> {
+        if self.rows == 0 && !self.names.is_empty() {
+            writeln!(self.out, "(No rows returned)")?;
+        }
+        self.out.flush()?;
+        Ok(())
+    }
+}
+
+struct RecordSink {
+    out: Box<dyn Write + Send>,
+    timestamps: TimestampFormat,
+    columns: Vec<ColumnMeta>,
+    rows: usize,
+}
+
+impl RecordSink {
+    fn new(out: Box<dyn Write + Send>, timestamps: TimestampFormat) -> Self {
+        Self { out, timestamps, columns: Vec::new(), rows: 0 }
+    }
+}
+
+impl ResultSink for RecordSink {
+    fn begin(&mut self, columns: &[ColumnMeta]) -> Result<()> {
+        self.columns = columns.to_vec();
+        Ok(())
+    }
+
+    fn row(&mut self, values: &[Value]) -> Result<()> {
+        self.rows += 1;
+        let record: Vec<String> = self
+            .columns
+            .iter()
+            .zip(values)
+            .map(|(column, value)| format!("{}: {}", column.name, value.render(&self.timestamps)))
+            .collect();
+        writeln!(self.out, "{}", record.join(", "))?;
+        Ok(())
+    }
+
+    fn end(&mut self) -> Result<()> {
+        if self.rows == 0 && !self.columns.is_empty() {
+            writeln!(self.out, "(No rows returned)")?;
+        }
+        self.out.flush()?;
+        Ok(())
+    }
+}
+
+/// Builds a row object. Keys keep the column order, as serde_json is built
+/// with `preserve_order`.
+fn json_object(keys: &[String], values: &[Value], timestamps: &TimestampFormat) -> serde_json::Value {
+    let object = keys
+        .iter()
+        .zip(values)
+        .map(|(key, value)| (key.clone(), value.to_json(timestamps)))
+        .collect();
+    serde_json::Value::Object(object)
+}
+
+/// Column names as object keys. A name used by an earlier column, as in a
+/// join selecting two `id`s, gets a suffix: `id`, `id_2`.
+fn unique_keys(columns: &[ColumnMeta]) -> Vec<String> {
+    let mut keys: Vec<String> = Vec::with_capacity(columns.len());
+    for col in columns {
+        let key = if keys.contains(&col.name) {
+            (2..)
+                .map(|n| format!("{}_{}", col.name, n))
+                .find(|key| !keys.contains(key) && !columns.iter().any(|c| &c.name == key))
+                .unwrap()
+        } else {
+            col.name.clone()
+        };
+        keys.push(key);
+    }
+    keys
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::value::ColumnType;
+
+    /// Output that stays readable after the sink that wrote it is gone.
+    #[derive(Clone, Default)]
+    struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Buffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn columns(names: &[(&str, ColumnType)]) -> Vec<ColumnMeta> {
+        names
+            .iter()
+            .map(|&(name, column_type)| ColumnMeta { name: name.to_string(), column_type })
+            .collect()
+    }
+
+    /// Renders a result through the sink of `settings`.
+    fn render(settings: &OutputSettings, columns: &[ColumnMeta], rows: &[Vec<Value>]) -> String {
+        let buffer = Buffer::default();
+        let mut sink = OutputFormat::from_str(&settings.format).sink(Box::new(buffer.clone()), settings, false);
+        sink.begin(columns).unwrap();
+        for row in rows {
+            sink.row(row).unwrap();
+        }
+        sink.end().unwrap();
+        drop(sink);
+        let bytes = buffer.0.lock().unwrap().clone();
+        String::from_utf8(bytes).unwrap()
+    }
+
+    #[test]
+    fn json_objects_keep_column_order() {
+        let mut settings = OutputSettings::new("ndjson", None);
+        settings.json.layout = JsonLayout::Object;
+        let columns = columns(&[("id", ColumnType::Long), ("name", ColumnType::Varchar), ("dbl", ColumnType::Double)]);
+        let rows = vec![vec![Value::Int(1), Value::Text("a".into()), Value::Double(2.5)]];
+        assert_eq!(render(&settings, &columns, &rows), "{\"id\":1,\"name\":\"a\",\"dbl\":2.5}\n");
+    }
+
+    #[test]
+    fn json_objects_suffix_duplicate_columns() {
+        let settings = OutputSettings::new("ndjson", None);
+        let columns = columns(&[("id", ColumnType::Long), ("id", ColumnType::Long), ("id_2", ColumnType::Long)]);
+        let rows = vec![vec![Value::Int(1), Value::Int(2), Value::Int(3)]];
+        assert_eq!(render(&settings, &columns, &rows), "{\"id\":1,\"id_3\":2,\"id_2\":3}\n");
+    }
+
+    fn sample() -> (Vec<ColumnMeta>, Vec<Vec<Value>>) {
+        let columns = columns(&[("id", ColumnType::Long), ("name", ColumnType::Varchar)]);
+        let rows = vec![
+            vec![Value::Int(1), Value::Text("a".into())],
+            vec![Value::Int(2), Value::Null],
+        ];
+        (columns, rows)
+    }
+
+    #[test]
+    fn json_arrays_pretty_and_compact() {
+        let (columns, rows) = sample();
+        let mut settings = OutputSettings::new("json", None);
+        assert_eq!(
+            render(&settings, &columns, &rows),
+            "[\n  {\n    \"id\": 1,\n    \"name\": \"a\"\n  },\n  {\n    \"id\": 2,\n    \"name\": null\n  }\n]\n"
+        );
+        settings.json.pretty = false;
+        assert_eq!(render(&settings, &columns, &rows), "[{\"id\":1,\"name\":\"a\"},{\"id\":2,\"name\":null}]\n");
+        assert_eq!(render(&settings, &columns, &[]), "[]\n");
+        assert_eq!(render(&settings, &[], &[]), "");
+    }
+
+    #[test]
+    fn json_envelope_keeps_duplicate_names() {
+        let mut settings = OutputSettings::new("ndjson", None);
+        settings.json.layout = JsonLayout::Envelope;
+        let columns = columns(&[("id", ColumnType::Long), ("id", ColumnType::Long)]);
+        let rows = vec![vec![Value::Int(1), Value::Null]];
+        assert_eq!(render(&settings, &columns, &rows), "{\"columns\":[\"id\",\"id\"],\"values\":[1,null]}\n");
+    }
+
+    #[test]
+    fn csv_writes_header_and_quotes() {
+        let settings = OutputSettings::new("csv", None);
+        let columns = columns(&[("id", ColumnType::Long), ("note", ColumnType::Varchar)]);
+        let rows = vec![vec![Value::Int(1), Value::Text("a, \"b\"".into())]];
+        assert_eq!(render(&settings, &columns, &rows), "id,note\n1,\"a, \"\"b\"\"\"\n");
+    }
+
+    #[test]
+    fn table_sizes_columns_to_content() {
+        let (columns, rows) = sample();
+        let settings = OutputSettings::new("table", None);
+        assert_eq!(
+            render(&settings, &columns, &rows),
+            "+----+------+\n| id | name |\n+----+------+\n| 1  | a    |\n| 2  | NULL |\n+----+------+\n"
+        );
+        assert_eq!(render(&settings, &columns, &[]), "(No rows returned)\n");
+        assert_eq!(render(&settings, &[], &[]), "");
+    }
+
+    #[test]
+    fn table_widens_columns_after_the_buffered_rows() {
+        let settings = OutputSettings::new("table", None);
+        let columns = columns(&[("v", ColumnType::Varchar)]);
+        let mut rows = vec![vec![Value::Text("x".into())]; TABLE_BUFFER_ROWS];
+        rows.push(vec![Value::Text("wide".into())]);
+        let output = render(&settings, &columns, &rows);
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines[3], "| x |");
+        assert_eq!(&lines[lines.len() - 3..], ["+------+", "| wide |", "+------+"]);
+    }
+
+    #[test]
+    fn table_truncates_wraps_or_turns_vertical_to_fit() {
+        let columns = columns(&[("id", ColumnType::Long), ("text", ColumnType::Varchar)]);
+        let rows = vec![vec![Value::Int(1), Value::Text("abcdefghijklmnop".into())]];
+        let mut settings = OutputSettings::new("table", None);
+        settings.width = TableWidth::Columns(20);
+        assert!(render(&settings, &columns, &rows).contains("| 1  | abcdefghij… |"));
+
+        settings.wrap = true;
+        let output = render(&settings, &columns, &rows);
+        assert!(output.contains("| 1  | abcdefghijk |\n|    | lmnop       |"));
+
+        settings.width = TableWidth::Columns(10);
+        assert_eq!(render(&settings, &columns, &rows), "Row 1:\n  id: 1\n  text: abcdefghijklmnop\n\n");
+    }
+
+    #[test]
+    fn fit_widths_shrinks_widest_columns_first() {
+        let mut widths = vec![4, 30, 20];
+        assert!(fit_widths(&mut widths, 40));
+        assert_eq!(widths, [4, 13, 13]);
+        let mut widths = vec![20, 20];
+        assert!(!fit_widths(&mut widths, 10));
+    }
+
+    #[test]
+    fn vertical_and_record_rows() {
+        let (columns, rows) = sample();
+        let vertical = render(&OutputSettings::new("vertical", None), &columns, &rows);
+        assert_eq!(vertical, "Row 1:\n  id: 1\n  name: a\n\nRow 2:\n  id: 2\n  name: NULL\n\n");
+        let record = render(&OutputSettings::new("record", None), &columns, &rows);
+        assert_eq!(record, "id: 1, name: a\nid: 2, name: NULL\n");
+        assert_eq!(render(&OutputSettings::new("record", None), &columns, &[]), "(No rows returned)\n");
+    }
 }
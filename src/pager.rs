@@ -0,0 +1,188 @@
+// src/pager.rs
+
+use std::io::{self, Write};
+use std::process::{Child, Command, Stdio};
+use terminal_size::{terminal_size, Height, Width};
+
+/// When query output is shown through a pager.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PagerMode {
+    /// Always page output to a terminal.
+    On,
+    /// Never page.
+    Off,
+    /// Page only when the output is taller than the terminal.
+    Auto,
+}
+
+impl PagerMode {
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "on" => Some(PagerMode::On),
+            "off" => Some(PagerMode::Off),
+            "auto" => Some(PagerMode::Auto),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for PagerMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PagerMode::On => f.write_str("on"),
+            PagerMode::Off => f.write_str("off"),
+            PagerMode::Auto => f.write_str("auto"),
+        }
+    }
+}
+
+/// Terminal size as (columns, rows), if stdout is a terminal.
+pub fn terminal_dimensions() -> Option<(usize, usize)> {
+    terminal_size().map(|(Width(w), Height(h))| (w as usize, h as usize))
+}
+
+enum State {
+    /// Still deciding: output is held back until it overflows the screen.
+    Buffering,
+    /// Output goes to the pager's stdin.
+    Paging(Child),
+    /// Output goes straight to stdout.
+    Direct,
+}
+
+/// Writer for terminal output that hands it to `$PAGER` (or `less`) once
+/// it no longer fits on the screen.
+///
+/// In `Auto` mode at most one screenful is buffered; if the output ends
+/// before that, it is printed directly when the writer is dropped.
+pub struct PagerWriter {
+    state: State,
+    buffer: Vec<u8>,
+    width: usize,
+    rows: usize,
+    lines: usize,
+    column: usize,
+}
+
+impl PagerWriter {
+    pub fn new(mode: PagerMode) -> Self {
+        let (width, rows) = terminal_dimensions().unwrap_or((80, 24));
+        let mut writer = Self {
+            state: State::Buffering,
+            buffer: Vec::new(),
+            width: width.max(1),
+            // keep a line free for the prompt
+            rows: rows.saturating_sub(1).max(1),
+            lines: 0,
+            column: 0,
+        };
+        match mode {
+            PagerMode::On => writer.start_pager(),
+            PagerMode::Off => writer.state = State::Direct,
+            PagerMode::Auto => {}
+        }
+        writer
+    }
+
+    /// Spawns the pager, falling back to direct output if it can't start.
+    fn start_pager(&mut self) {
+        let command = std::env::var("PAGER")
+            .ok()
+            .filter(|p| !p.trim().is_empty())
+            .unwrap_or_else(|| "less".to_string());
+        let mut parts = command.split_whitespace();
+        let program = parts.next().unwrap_or("less");
+        let mut cmd = Command::new(program);
+        cmd.args(parts).stdin(Stdio::piped());
+        if std::env::var_os("LESS").is_none() {
+            // quit if one screen, raw colours, chop long lines, keep screen
+            cmd.env("LESS", "FRSX");
+        }
+        self.state = match cmd.spawn() {
+            Ok(child) => State::Paging(child),
+            Err(_) => State::Direct,
+        };
+    }
+
+    /// Counts screen rows in `buf`, wrapping lines wider than the terminal.
+    fn count_rows(&mut self, buf: &[u8]) {
+        for &b in buf {
+            if b == b'\n' {
+                self.lines += 1;
+                self.column = 0;
+            } else if b & 0xC0 != 0x80 {
+                // count characters, not UTF-8 continuation bytes
+                self.column += 1;
+                if self.column > self.width {
+                    self.lines += 1;
+                    self.column = 1;
+                }
+            }
+        }
+    }
+
+    fn flush_buffer(&mut self) -> io::Result<()> {
+        let buffer = std::mem::take(&mut self.buffer);
+        if buffer.is_empty() {
+            return Ok(());
+        }
+        self.write_through(&buffer)
+    }
+
+    fn write_through(&mut self, buf: &[u8]) -> io::Result<()> {
+        match &mut self.state {
+            State::Paging(child) => match child.stdin.as_mut() {
+                Some(stdin) => stdin.write_all(buf),
+                None => Err(io::Error::from(io::ErrorKind::BrokenPipe)),
+            },
+            _ => io::stdout().write_all(buf),
+        }
+    }
+}
+
+impl Write for PagerWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if let State::Buffering = self.state {
+            self.buffer.extend_from_slice(buf);
+            self.count_rows(buf);
+            if self.lines >= self.rows {
+                self.start_pager();
+                self.flush_buffer()?;
+            }
+            return Ok(buf.len());
+        }
+        self.flush_buffer()?;
+        self.write_through(buf)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match &mut self.state {
+            // Nothing is shown until we know whether the output fits.
+            State::Buffering => Ok(()),
+            State::Paging(child) => match child.stdin.as_mut() {
+                Some(stdin) => stdin.flush(),
+                None => Ok(()),
+            },
+            State::Direct => io::stdout().flush(),
+        }
+    }
+}
+
+impl Drop for PagerWriter {
+    fn drop(&mut self) {
+        match std::mem::replace(&mut self.state, State::Direct) {
+            State::Buffering => {
+                let mut stdout = io::stdout();
+                let _ = stdout.write_all(&self.buffer);
+                let _ = stdout.flush();
+            }
+            State::Paging(mut child) => {
+                // Closing stdin tells the pager the output is complete.
+                drop(child.stdin.take());
+                let _ = child.wait();
+            }
+            State::Direct => {}
+        }
+    }
+}
@@ -2,32 +2,47 @@
 
 use crate::commands::{execute_query_command, handle_meta_command};
 use crate::completer::SQLCompleter;
+use crate::config::Config;
+use crate::db::Connection;
+use crate::highlight::Styles;
+use crate::session::Sessions;
+use crate::output::OutputSettings;
+use crate::pager::PagerMode;
+use crate::sql;
 use rustyline::error::ReadlineError;
 use rustyline::Editor;
 use tokio::signal;
-use tokio_postgres::{Client, NoTls};
 use std::sync::{Arc, Mutex as StdMutex};
 use tokio::sync::Mutex as TokioMutex;
 use anyhow::Result;
 use shellexpand::tilde;
 use std::io::Write;
-
-pub async fn start_repl(client: Client, default_format: &str, history_file: &str) -> Result<()> {
+use std::time::Duration;
+use tokio::sync::watch;
+
+pub async fn start_repl(
+    mut sessions: Sessions,
+    mut settings: OutputSettings,
+    config: Config,
+    history_file: &str,
+    refresh_interval: Option<Duration>,
+) -> Result<()> {
     let mut completer = SQLCompleter::new();
+    completer.styles = Styles::for_terminal(&config.theme)?;
 
-    // Update table names for auto-completion
-    if let Err(e) = completer.update_tables(&client).await {
-        eprintln!("Failed to fetch table names: {}", e);
+    // Fetch table, column and function names for auto-completion
+    if let Err(e) = completer.update_metadata(sessions.connection()).await {
+        eprintln!("Failed to fetch completion metadata: {}", e);
     }
 
-    // Wrap the completer in an Arc and Tokio Mutex for shared access across tasks
-    let completer = Arc::new(TokioMutex::new(completer));
-
-    // Initialize the rustyline editor with the completer
+    // The editor's copy shares the metadata, so refreshes reach completion
     let mut rl = Editor::<SQLCompleter>::new();
-    {
-        let completer_lock = completer.lock().await;
-        rl.set_helper(Some(completer_lock.clone()));
+    rl.set_helper(Some(completer.clone()));
+
+    // Metadata refreshes in the background go to the active connection
+    let (active, active_receiver) = watch::channel(sessions.connection().clone());
+    if let Some(interval) = refresh_interval {
+        completer.refresh_periodically(active_receiver, interval);
     }
 
     // Wrap the rl in an Arc<StdMutex<Editor>>
@@ -44,71 +59,87 @@ pub async fn start_repl(client: Client, default_format: &str, history_file: &str
 
     println!("Connected to QuestDB. Type '\\q' to quit.");
 
-    // Wrap the client in an Arc for shared ownership
-    let client = Arc::new(client);
+    // Long results in the REPL go through the pager when they overflow the screen
+    settings.pager = PagerMode::Auto;
 
-    // Initialize the output format, wrapped in an Arc and Tokio Mutex for thread-safe access
-    let format = Arc::new(TokioMutex::new(default_format.to_string()));
+    // Initialize the output settings, wrapped in an Arc and Tokio Mutex for thread-safe access
+    let settings = Arc::new(TokioMutex::new(settings));
 
     loop {
+        // \use, \connect and reconnects change the active connection
+        active.send_replace(sessions.connection().clone());
+        let prompt = prompt_for(&sessions);
         tokio::select! {
             // Listen for incoming SIGINT (Ctrl+C)
             _ = signal::ctrl_c() => {
                 println!("\nReceived Ctrl+C. Cancelling ongoing query if any.");
-                let cancel_token = client.cancel_token();
-                let _ = cancel_token.cancel_query(NoTls).await;
+                let _ = sessions.connection().cancel().await;
             },
             // Listen for user input in the REPL
             maybe_line = tokio::task::spawn_blocking({
                 let rl = Arc::clone(&rl);
+                let prompt = prompt.clone();
                 move || {
                     let mut rl = rl.lock().unwrap();
-                    rl.readline("questdb> ")
+                    rl.readline(&prompt)
                 }
             }) => {
                 match maybe_line {
                     Ok(line_result) => {
                         match line_result {
                             Ok(line) => {
-                                let mut rl_guard = rl.lock().unwrap();
-                                rl_guard.add_history_entry(line.as_str());
-
                                 let trimmed = line.trim();
-                                if trimmed == "\\q" {
-                                    println!("Goodbye!");
-                                    break;
-                                } else if trimmed.starts_with('\\') {
+                                if trimmed.is_empty() {
+                                    continue;
+                                }
+                                // Meta commands take a line of their own
+                                if trimmed.starts_with('\\') {
+                                    rl.lock().unwrap().add_history_entry(trimmed);
+                                    if trimmed == "\\q" {
+                                        println!("Goodbye!");
+                                        break;
+                                    }
+                                    if sessions.connection().is_closed()
+                                        && !restore_connection(sessions.connection_mut(), &completer).await
+                                    {
+                                        continue;
+                                    }
                                     // Handle meta commands (e.g., \help, \format)
-                                    let mut completer_lock = completer.lock().await;
-                                    let mut format_lock = format.lock().await;
-                                    handle_meta_command(&client, trimmed, &mut *completer_lock, &mut *format_lock).await;
-                                } else if !trimmed.is_empty() {
-                                    // Execute SQL query
-                                    let query = trimmed.to_string();
-                                    let client_clone = Arc::clone(&client);
-                                    let format_clone = Arc::clone(&format);
-
-                                    let query_task = tokio::spawn(async move {
-                                        execute_query_command(&client_clone, &query, &format_clone.lock().await).await
-                                    });
-
-                                    tokio::select! {
-                                        result = query_task => {
-                                            if let Err(e) = result {
-                                                eprintln!("Query execution error: {:?}", e);
-                                            }
-                                        },
-                                        _ = signal::ctrl_c() => {
-                                            eprintln!("\nQuery canceled.");
-                                            let cancel_token = client.cancel_token();
-                                            let _ = cancel_token.cancel_query(NoTls).await;
+                                    let mut settings_lock = settings.lock().await;
+                                    handle_meta_command(&mut sessions, trimmed, &completer, &mut settings_lock, &config).await;
+                                    continue;
+                                }
+
+                                // the validator only accepts input whose statements all end in `;`
+                                let (statements, _) = sql::split_statements(&line);
+                                let statements: Vec<String> = statements
+                                    .into_iter()
+                                    .filter(|s| !sql::is_blank(s))
+                                    .map(str::to_string)
+                                    .collect();
+                                rl.lock().unwrap().add_history_entry(trimmed);
+
+                                for query in statements {
+                                    if sessions.connection().is_closed()
+                                        && !restore_connection(sessions.connection_mut(), &completer).await
+                                    {
+                                        break;
+                                    }
+                                    let ddl = sql::is_ddl(&query);
+                                    if !run_statement(&mut sessions, &settings, &completer, query).await {
+                                        break;
+                                    }
+                                    // new or changed tables show up in completion right away
+                                    if ddl {
+                                        if let Err(e) = completer.update_metadata(sessions.connection()).await {
+                                            eprintln!("Failed to fetch completion metadata: {}", e);
                                         }
                                     }
-
-                                    // Ensure the prompt reappears
-                                    print!("questdb> ");
-                                    std::io::stdout().flush().unwrap();
                                 }
+
+                                // Ensure the prompt reappears
+                                print!("{}", prompt_for(&sessions));
+                                std::io::stdout().flush().unwrap();
                             },
                             Err(ReadlineError::Interrupted) => {
                                 println!("Use \\q to quit.");
@@ -142,3 +173,78 @@ pub async fn start_repl(client: Client, default_format: &str, history_file: &str
 
     Ok(())
 }
+
+/// The prompt names the connection queries go to.
+fn prompt_for(sessions: &Sessions) -> String {
+    format!("questdb:{}> ", sessions.active().name)
+}
+
+/// Runs one statement on the active connection. Ctrl+C cancels it;
+/// returns false if it was cancelled.
+async fn run_statement(
+    sessions: &mut Sessions,
+    settings: &Arc<TokioMutex<OutputSettings>>,
+    completer: &SQLCompleter,
+    query: String,
+) -> bool {
+    let connection = sessions.connection().clone();
+    let settings_clone = Arc::clone(settings);
+
+    let query_task = tokio::spawn(async move {
+        let settings = settings_clone.lock().await.clone();
+        execute_query_command(&connection, &query, &settings).await
+    });
+
+    tokio::select! {
+        result = query_task => {
+            match result {
+                Ok(Err(e)) => {
+                    eprintln!("Error executing query: {}", e);
+                    if sessions.connection().is_closed() && restore_connection(sessions.connection_mut(), completer).await {
+                        eprintln!("The statement may not have completed; run it again if needed.");
+                    }
+                }
+                Err(e) => eprintln!("Query execution error: {:?}", e),
+                Ok(Ok(())) => {}
+            }
+            true
+        },
+        _ = signal::ctrl_c() => {
+            eprintln!("\nQuery canceled.");
+            let _ = sessions.connection().cancel().await;
+            false
+        }
+    }
+}
+
+/// Re-opens a lost connection and restores the session. Output settings
+/// live on our side and carry over; completion metadata is fetched again.
+async fn restore_connection(connection: &mut Connection, completer: &SQLCompleter) -> bool {
+    eprintln!("Connection to {} lost. Reconnecting...", connection.address());
+    let result = tokio::select! {
+        result = connection.reconnect() => result,
+        _ = signal::ctrl_c() => {
+            eprintln!("\nReconnect cancelled.");
+            return false;
+        }
+    };
+    match result {
+        Ok(attempts) => {
+            let plural = if attempts == 1 { "" } else { "s" };
+            println!(
+                "Reconnected to {} after {} attempt{}; session settings restored.",
+                connection.address(),
+                attempts,
+                plural
+            );
+            if let Err(e) = completer.update_metadata(connection).await {
+                eprintln!("Failed to fetch completion metadata: {}", e);
+            }
+            true
+        }
+        Err(e) => {
+            eprintln!("Could not reconnect: {:#}", e);
+            false
+        }
+    }
+}
@@ -0,0 +1,72 @@
+// src/session.rs
+
+use crate::db::Connection;
+use anyhow::{anyhow, Result};
+
+/// A named connection opened in the REPL.
+pub struct Session {
+    pub name: String,
+    /// Profile the connection was opened with, if any.
+    pub profile: Option<String>,
+    pub connection: Connection,
+}
+
+/// The connections open in the REPL. Queries and meta commands go to the
+/// active one; the others stay open until the REPL exits.
+pub struct Sessions {
+    sessions: Vec<Session>,
+    active: usize,
+}
+
+impl Sessions {
+    pub fn new(session: Session) -> Self {
+        Self { sessions: vec![session], active: 0 }
+    }
+
+    pub fn active(&self) -> &Session {
+        &self.sessions[self.active]
+    }
+
+    pub fn active_mut(&mut self) -> &mut Session {
+        &mut self.sessions[self.active]
+    }
+
+    pub fn connection(&self) -> &Connection {
+        &self.active().connection
+    }
+
+    pub fn connection_mut(&mut self) -> &mut Connection {
+        &mut self.active_mut().connection
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (bool, &Session)> {
+        self.sessions.iter().enumerate().map(move |(i, s)| (i == self.active, s))
+    }
+
+    /// `base`, or `base-2`, `base-3`, ... if that name is taken.
+    pub fn unique_name(&self, base: &str) -> String {
+        let taken = |name: &str| self.sessions.iter().any(|s| s.name == name);
+        if !taken(base) {
+            return base.to_string();
+        }
+        (2..)
+            .map(|n| format!("{}-{}", base, n))
+            .find(|name| !taken(name))
+            .unwrap()
+    }
+
+    /// Adds a session and makes it the active one.
+    pub fn add(&mut self, session: Session) {
+        self.sessions.push(session);
+        self.active = self.sessions.len() - 1;
+    }
+
+    pub fn switch(&mut self, name: &str) -> Result<()> {
+        self.active = self
+            .sessions
+            .iter()
+            .position(|s| s.name == name)
+            .ok_or_else(|| anyhow!("no connection named '{}'; see \\conns", name))?;
+        Ok(())
+    }
+}
@@ -0,0 +1,251 @@
+// src/sql.rs
+
+/// Lexical class of a piece of SQL text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenKind {
+    Whitespace,
+    /// A keyword or an unquoted identifier.
+    Word,
+    /// A `"quoted"` identifier.
+    QuotedIdent,
+    /// A `'string'` literal.
+    String,
+    /// A number, including suffixed ones such as `1h` in `SAMPLE BY 1h`.
+    Number,
+    /// A `-- line` or `/* block */` comment.
+    Comment,
+    /// Any other single character: operators, parentheses, `;`, ...
+    Punct,
+}
+
+/// A token of SQL text, as a byte range into it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub start: usize,
+    pub end: usize,
+    /// False for a string, quoted identifier or block comment still open
+    /// at the end of the text.
+    pub terminated: bool,
+}
+
+/// Splits SQL text into tokens covering all of it.
+pub fn tokenize(sql: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut chars = sql.char_indices().peekable();
+    while let Some((start, c)) = chars.next() {
+        let next = chars.peek().map(|&(_, c)| c);
+        let mut terminated = true;
+        let kind = match c {
+            c if c.is_whitespace() => {
+                while chars.next_if(|&(_, c)| c.is_whitespace()).is_some() {}
+                TokenKind::Whitespace
+            }
+            '-' if next == Some('-') => {
+                while chars.next_if(|&(_, c)| c != '\n').is_some() {}
+                TokenKind::Comment
+            }
+            '/' if next == Some('*') => {
+                chars.next();
+                terminated = false;
+                let mut star = false;
+                for (_, c) in chars.by_ref() {
+                    if star && c == '/' {
+                        terminated = true;
+                        break;
+                    }
+                    star = c == '*';
+                }
+                TokenKind::Comment
+            }
+            '\'' | '"' => {
+                // the quote is escaped by doubling it
+                terminated = false;
+                while let Some((_, d)) = chars.next() {
+                    if d == c && chars.next_if(|&(_, d)| d == c).is_none() {
+                        terminated = true;
+                        break;
+                    }
+                }
+                if c == '\'' {
+                    TokenKind::String
+                } else {
+                    TokenKind::QuotedIdent
+                }
+            }
+            c if c.is_ascii_digit() || (c == '.' && next.is_some_and(|n| n.is_ascii_digit())) => {
+                let mut prev = c;
+                while let Some((_, d)) = chars.next_if(|&(_, d)| {
+                    d.is_alphanumeric() || d == '_' || d == '.' || (matches!(d, '+' | '-') && matches!(prev, 'e' | 'E'))
+                }) {
+                    prev = d;
+                }
+                TokenKind::Number
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                while chars.next_if(|&(_, c)| c.is_alphanumeric() || c == '_' || c == '$').is_some() {}
+                TokenKind::Word
+            }
+            _ => TokenKind::Punct,
+        };
+        let end = chars.peek().map_or(sql.len(), |&(i, _)| i);
+        tokens.push(Token { kind, start, end, terminated });
+    }
+    tokens
+}
+
+/// Splits input into the complete statements it holds, without their
+/// terminating `;`, and the unfinished rest. A `;` only ends a statement
+/// outside strings, comments and parentheses.
+pub fn split_statements(input: &str) -> (Vec<&str>, &str) {
+    let mut statements = Vec::new();
+    let mut start = 0;
+    let mut depth = 0usize;
+    for token in tokenize(input) {
+        if token.kind != TokenKind::Punct {
+            continue;
+        }
+        match &input[token.start..token.end] {
+            "(" => depth += 1,
+            // unbalanced parentheses are left for the server to report
+            ")" => depth = depth.saturating_sub(1),
+            ";" if depth == 0 => {
+                statements.push(input[start..token.start].trim());
+                start = token.end;
+            }
+            _ => {}
+        }
+    }
+    (statements, &input[start..])
+}
+
+/// Whether the text holds nothing but whitespace and complete comments.
+pub fn is_blank(sql: &str) -> bool {
+    tokenize(sql)
+        .iter()
+        .all(|t| t.terminated && matches!(t.kind, TokenKind::Whitespace | TokenKind::Comment))
+}
+
+/// Whether a statement changes the schema, so that table and column
+/// names known from before may be stale.
+pub fn is_ddl(sql: &str) -> bool {
+    tokenize(sql)
+        .iter()
+        .find(|t| !matches!(t.kind, TokenKind::Whitespace | TokenKind::Comment))
+        .is_some_and(|t| {
+            t.kind == TokenKind::Word
+                && ["CREATE", "DROP", "ALTER", "RENAME"].iter().any(|k| k.eq_ignore_ascii_case(&sql[t.start..t.end]))
+        })
+}
+
+/// Keywords of QuestDB's SQL dialect, including its type names.
+pub const KEYWORDS: &[&str] = &[
+    "ADD", "ALIGN", "ALL", "ALTER", "AND", "AS", "ASC", "ASOF", "ATTACH", "BACKUP", "BETWEEN", "BINARY",
+    "BOOLEAN", "BY", "BYPASS", "BYTE", "CACHE", "CALENDAR", "CANCEL", "CAPACITY", "CASE", "CAST", "CHAR",
+    "COLUMN", "COPY", "CREATE", "CROSS", "CURRENT", "DATABASE", "DATE", "DAY", "DEDUP", "DELETE", "DESC",
+    "DETACH", "DISTINCT", "DOUBLE", "DROP", "ELSE", "END", "EXCEPT", "EXISTS", "EXPLAIN", "FALSE", "FILL",
+    "FLOAT", "FOLLOWING", "FROM", "FULL", "GEOHASH", "GROUP", "HAVING", "HOUR", "IF", "ILIKE", "IN", "INDEX",
+    "INNER", "INSERT", "INT", "INTERSECT", "INTERVAL", "INTO", "IPV4", "IS", "JOIN", "KEYS", "LATEST", "LEFT",
+    "LIKE", "LIMIT", "LINEAR", "LONG", "LONG256", "LT", "MONTH", "NOCACHE", "NONE", "NOT", "NULL", "OFFSET",
+    "ON", "OR", "ORDER", "OUTER", "OVER", "PARAM", "PARTITION", "PARTITIONS", "PRECEDING", "PREV", "QUERY",
+    "RANGE", "REINDEX", "RENAME", "RESUME", "RIGHT", "ROW", "ROWS", "SAMPLE", "SELECT", "SET", "SHORT",
+    "SHOW", "SPLICE", "SQUASH", "STRING", "SUSPEND", "SYMBOL", "TABLE", "TABLES", "THEN", "TIME",
+    "TIMESTAMP", "TO", "TRUE", "TRUNCATE", "TTL", "TYPE", "UNBOUNDED", "UNION", "UPDATE", "UPSERT", "UUID",
+    "VACUUM", "VALUES", "VARCHAR", "WAL", "WEEK", "WHEN", "WHERE", "WITH", "YEAR", "ZONE",
+];
+
+/// Whether a word is a keyword, in any case.
+pub fn is_keyword(word: &str) -> bool {
+    KEYWORDS.iter().any(|k| k.eq_ignore_ascii_case(word))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(sql: &str) -> Vec<(TokenKind, &str)> {
+        tokenize(sql)
+            .into_iter()
+            .filter(|t| t.kind != TokenKind::Whitespace)
+            .map(|t| (t.kind, &sql[t.start..t.end]))
+            .collect()
+    }
+
+    #[test]
+    fn tokenize_classifies_tokens() {
+        use TokenKind::*;
+        assert_eq!(
+            kinds("SELECT x1, 'it''s', 1.5e-3, \"T x\" FROM t SAMPLE BY 1h -- done"),
+            vec![
+                (Word, "SELECT"),
+                (Word, "x1"),
+                (Punct, ","),
+                (String, "'it''s'"),
+                (Punct, ","),
+                (Number, "1.5e-3"),
+                (Punct, ","),
+                (QuotedIdent, "\"T x\""),
+                (Word, "FROM"),
+                (Word, "t"),
+                (Word, "SAMPLE"),
+                (Word, "BY"),
+                (Number, "1h"),
+                (Comment, "-- done"),
+            ]
+        );
+    }
+
+    #[test]
+    fn tokenize_covers_all_text_and_marks_unterminated() {
+        let sql = "a /* b */ 'c";
+        let tokens = tokenize(sql);
+        assert_eq!(tokens.first().unwrap().start, 0);
+        assert_eq!(tokens.last().unwrap().end, sql.len());
+        assert!(tokens.windows(2).all(|w| w[0].end == w[1].start));
+        assert!(tokens[2].terminated);
+        assert!(!tokens.last().unwrap().terminated);
+        assert!(!tokenize("/* open").last().unwrap().terminated);
+    }
+
+    #[test]
+    fn split_ignores_semicolons_in_strings_and_identifiers() {
+        let (statements, rest) = split_statements("select ';', 'it''s; ok'; select \"a;b\" from t;");
+        assert_eq!(statements, vec!["select ';', 'it''s; ok'", "select \"a;b\" from t"]);
+        assert_eq!(rest, "");
+    }
+
+    #[test]
+    fn split_ignores_semicolons_in_comments() {
+        let (statements, rest) = split_statements("select 1 -- a;b\n; select /* ; */ 2; sel");
+        assert_eq!(statements, vec!["select 1 -- a;b", "select /* ; */ 2"]);
+        assert_eq!(rest, " sel");
+    }
+
+    #[test]
+    fn split_ignores_semicolons_in_parentheses() {
+        let (statements, rest) = split_statements("select f((1; 2), 3); select (");
+        assert_eq!(statements, vec!["select f((1; 2), 3)"]);
+        assert_eq!(rest, " select (");
+
+        // an unbalanced ')' doesn't swallow later statements
+        let (statements, _) = split_statements("select 1); select 2;");
+        assert_eq!(statements, vec!["select 1)", "select 2"]);
+    }
+
+    #[test]
+    fn split_keeps_unfinished_string_in_rest() {
+        let (statements, rest) = split_statements("select 'a;\nb");
+        assert!(statements.is_empty());
+        assert_eq!(rest, "select 'a;\nb");
+    }
+
+    #[test]
+    fn blank_and_ddl() {
+        assert!(is_blank("  -- note\n /* x */ "));
+        assert!(!is_blank("/* open"));
+        assert!(!is_blank("select 1"));
+        assert!(is_ddl("/* c */ create table t (x int)"));
+        assert!(is_ddl("ALTER TABLE t ADD COLUMN y INT"));
+        assert!(!is_ddl("select * from created"));
+    }
+}
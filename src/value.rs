@@ -0,0 +1,346 @@
+// src/value.rs
+
+use crate::decode::{self, Array};
+use anyhow::{anyhow, Result};
+use chrono::format::{Item, StrftimeItems};
+use chrono::{NaiveDate, NaiveDateTime};
+use chrono_tz::Tz;
+use std::fmt;
+use tokio_postgres::types::Type;
+use tokio_postgres::{Column, Row};
+
+/// QuestDB column type of a result column.
+///
+/// PGWire folds several QuestDB types onto the same PostgreSQL type (SYMBOL,
+/// GEOHASH and IPv4 all arrive as VARCHAR, BYTE as INT2), so the PGWire
+/// mapping can only be as precise as the wire allows. In particular
+/// `Symbol` only comes from HTTP `/exec` results; over PGWire SYMBOL columns
+/// are `Varchar`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColumnType {
+    Boolean,
+    Short,
+    Int,
+    Long,
+    Float,
+    Double,
+    Char,
+    Varchar,
+    /// Written as a dictionary-encoded string by the columnar formats.
+    Symbol,
+    Timestamp,
+    Date,
+    Uuid,
+    Long256,
+    Binary,
+    Interval,
+    DoubleArray,
+}
+
+impl ColumnType {
+    /// Maps the PostgreSQL type QuestDB reports over PGWire.
+    pub fn from_pg(ty: &Type) -> Self {
+        match *ty {
+            Type::BOOL => ColumnType::Boolean,
+            Type::INT2 => ColumnType::Short,
+            Type::INT4 => ColumnType::Int,
+            Type::INT8 => ColumnType::Long,
+            Type::FLOAT4 => ColumnType::Float,
+            Type::FLOAT8 => ColumnType::Double,
+            Type::CHAR | Type::BPCHAR => ColumnType::Char,
+            Type::TIMESTAMP | Type::TIMESTAMPTZ => ColumnType::Timestamp,
+            Type::DATE => ColumnType::Date,
+            Type::UUID => ColumnType::Uuid,
+            Type::NUMERIC => ColumnType::Long256,
+            Type::BYTEA => ColumnType::Binary,
+            Type::INTERVAL => ColumnType::Interval,
+            Type::FLOAT8_ARRAY | Type::FLOAT4_ARRAY | Type::INT2_ARRAY | Type::INT4_ARRAY | Type::INT8_ARRAY => {
+                ColumnType::DoubleArray
+            }
+            // VARCHAR, SYMBOL, GEOHASH, IPv4 and anything else sent as text
+            _ => ColumnType::Varchar,
+        }
+    }
+
+    /// Maps a type name reported by the HTTP `/exec` endpoint, such as
+    /// `TIMESTAMP`, `GEOHASH(8c)` or `DOUBLE[]`, onto the same types PGWire
+    /// results use so both transports render alike.
+    pub fn from_questdb(name: &str) -> Self {
+        let name = name.to_uppercase();
+        if name.ends_with("[]") || name.starts_with("ARRAY") {
+            return ColumnType::DoubleArray;
+        }
+        match name.as_str() {
+            "BOOLEAN" => ColumnType::Boolean,
+            "BYTE" | "SHORT" => ColumnType::Short,
+            "INT" => ColumnType::Int,
+            "LONG" => ColumnType::Long,
+            "FLOAT" => ColumnType::Float,
+            "DOUBLE" => ColumnType::Double,
+            "CHAR" => ColumnType::Char,
+            "SYMBOL" => ColumnType::Symbol,
+            // PGWire sends DATE as a TIMESTAMP too; TIMESTAMP_NS keeps its
+            // nanoseconds since /exec sends them as text
+            "TIMESTAMP" | "TIMESTAMP_NS" | "DATE" => ColumnType::Timestamp,
+            "UUID" => ColumnType::Uuid,
+            "LONG256" => ColumnType::Long256,
+            "BINARY" => ColumnType::Binary,
+            "INTERVAL" => ColumnType::Interval,
+            // STRING, VARCHAR, GEOHASH(...), IPv4
+            _ => ColumnType::Varchar,
+        }
+    }
+
+    /// Whether values of this type are numbers, e.g. for right-alignment.
+    pub fn is_numeric(&self) -> bool {
+        matches!(
+            self,
+            ColumnType::Short | ColumnType::Int | ColumnType::Long | ColumnType::Float | ColumnType::Double
+        )
+    }
+}
+
+/// Name and type of a result column.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ColumnMeta {
+    pub name: String,
+    pub column_type: ColumnType,
+}
+
+impl ColumnMeta {
+    pub fn from_pg(column: &Column) -> Self {
+        Self {
+            name: column.name().to_string(),
+            column_type: ColumnType::from_pg(column.type_()),
+        }
+    }
+}
+
+/// A single decoded column value, shared by every output format.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    /// BYTE, SHORT, INT and LONG.
+    Int(i64),
+    Float(f32),
+    Double(f64),
+    /// VARCHAR, SYMBOL, CHAR, LONG256, GEOHASH, IPv4, INTERVAL.
+    Text(String),
+    Timestamp(NaiveDateTime),
+    Date(NaiveDate),
+    Uuid(u128),
+    Binary(Vec<u8>),
+    Array(Array),
+}
+
+impl Value {
+    /// Decodes a raw PGWire value of the given PostgreSQL type.
+    pub fn decode(ty: &Type, raw: Option<&[u8]>) -> Result<Self> {
+        let raw = match raw {
+            Some(raw) => raw,
+            None => return Ok(Value::Null),
+        };
+        let value = match ColumnType::from_pg(ty) {
+            ColumnType::Boolean => Value::Bool(decode::boolean(raw)?),
+            ColumnType::Short | ColumnType::Int | ColumnType::Long => Value::Int(decode::int(raw)?),
+            ColumnType::Float => Value::Float(decode::float4(raw)?),
+            ColumnType::Double => Value::Double(decode::float8(raw)?),
+            ColumnType::Timestamp => Value::Timestamp(decode::timestamp(raw)?),
+            ColumnType::Date => Value::Date(decode::date(raw)?),
+            ColumnType::Uuid => Value::Uuid(decode::uuid(raw)?),
+            ColumnType::Long256 => Value::Text(decode::numeric(raw)?),
+            ColumnType::Interval => Value::Text(decode::interval(raw)?),
+            ColumnType::Binary => Value::Binary(raw.to_vec()),
+            ColumnType::DoubleArray => Value::Array(decode::array(raw)?),
+            ColumnType::Char | ColumnType::Varchar | ColumnType::Symbol => Value::Text(decode::text(raw)),
+        };
+        Ok(value)
+    }
+
+    /// Decodes a value from an HTTP `/exec` result of the given column type.
+    pub fn from_json(column_type: ColumnType, json: &serde_json::Value) -> Result<Self> {
+        use serde_json::Value as Json;
+        let invalid = || anyhow!("unexpected value {} for a {:?} column", json, column_type);
+        let value = match (column_type, json) {
+            (_, Json::Null) => Value::Null,
+            (ColumnType::Boolean, Json::Bool(b)) => Value::Bool(*b),
+            (ColumnType::Short | ColumnType::Int | ColumnType::Long, json) => {
+                Value::Int(json.as_i64().ok_or_else(invalid)?)
+            }
+            (ColumnType::Float, json) => Value::Float(json.as_f64().ok_or_else(invalid)? as f32),
+            (ColumnType::Double, json) => Value::Double(json.as_f64().ok_or_else(invalid)?),
+            (ColumnType::Timestamp, Json::String(s)) => Value::Timestamp(decode::parse_timestamp(s)?),
+            (ColumnType::Date, Json::String(s)) => Value::Date(decode::date(s.as_bytes())?),
+            (ColumnType::Uuid, Json::String(s)) => Value::Uuid(decode::uuid(s.as_bytes())?),
+            (ColumnType::DoubleArray, json @ Json::Array(_)) => Value::Array(decode::json_array(json)?),
+            (_, Json::String(s)) => Value::Text(s.clone()),
+            (_, other) => Value::Text(other.to_string()),
+        };
+        Ok(value)
+    }
+
+    /// Converts the value to JSON, keeping numbers and booleans native.
+    pub fn to_json(&self, timestamps: &TimestampFormat) -> serde_json::Value {
+        match self {
+            Value::Null => serde_json::Value::Null,
+            Value::Bool(b) => serde_json::Value::from(*b),
+            Value::Int(i) => serde_json::Value::from(*i),
+            Value::Float(f) => float_json(*f as f64),
+            Value::Double(f) => float_json(*f),
+            Value::Array(array) => array.to_json(),
+            other => serde_json::Value::String(other.render(timestamps)),
+        }
+    }
+
+    /// Renders the value as text, as shown by the text output formats.
+    pub fn render(&self, timestamps: &TimestampFormat) -> String {
+        match self {
+            Value::Null => "NULL".to_string(),
+            Value::Bool(b) => b.to_string(),
+            Value::Int(i) => i.to_string(),
+            Value::Float(v) => v.to_string(),
+            Value::Double(v) => v.to_string(),
+            Value::Text(s) => s.clone(),
+            Value::Timestamp(ts) => timestamps.format(ts),
+            Value::Date(d) => d.format("%Y-%m-%d").to_string(),
+            Value::Uuid(u) => {
+                let hex = format!("{:032x}", u);
+                format!(
+                    "{}-{}-{}-{}-{}",
+                    &hex[0..8],
+                    &hex[8..12],
+                    &hex[12..16],
+                    &hex[16..20],
+                    &hex[20..32]
+                )
+            }
+            Value::Binary(bytes) => format!("\\x{}", decode::hex(bytes)),
+            Value::Array(array) => array.to_string(),
+        }
+    }
+}
+
+fn float_json(f: f64) -> serde_json::Value {
+    serde_json::Number::from_f64(f).map_or(serde_json::Value::Null, serde_json::Value::Number)
+}
+
+/// Fractional-second precision of rendered timestamps.
+///
+/// `Nanos` shows the nanoseconds of TIMESTAMP_NS columns over HTTP. PGWire
+/// carries timestamps in microseconds, so there the last three digits are 0.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Precision {
+    Seconds,
+    Millis,
+    Micros,
+    Nanos,
+}
+
+impl Precision {
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "s" => Some(Precision::Seconds),
+            "ms" => Some(Precision::Millis),
+            "us" => Some(Precision::Micros),
+            "ns" => Some(Precision::Nanos),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Precision {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Precision::Seconds => f.write_str("s"),
+            Precision::Millis => f.write_str("ms"),
+            Precision::Micros => f.write_str("us"),
+            Precision::Nanos => f.write_str("ns"),
+        }
+    }
+}
+
+/// How timestamps are rendered by every output format.
+///
+/// QuestDB timestamps are UTC; they are shown in `timezone`, either as ISO
+/// 8601 with the given `precision` or with a custom strftime `pattern`.
+#[derive(Debug, Clone)]
+pub struct TimestampFormat {
+    pub timezone: Tz,
+    pub precision: Precision,
+    pub pattern: Option<String>,
+}
+
+impl Default for TimestampFormat {
+    fn default() -> Self {
+        Self {
+            timezone: Tz::UTC,
+            precision: Precision::Micros,
+            pattern: None,
+        }
+    }
+}
+
+impl TimestampFormat {
+    /// Parses an IANA time zone name such as `Europe/Madrid`.
+    pub fn parse_timezone(s: &str) -> Result<Tz> {
+        s.parse::<Tz>()
+            .map_err(|_| anyhow!("unknown time zone '{}'", s))
+    }
+
+    /// Applies a format spec: a precision (`s`, `ms`, `us`, `ns`), `iso` to
+    /// go back to ISO 8601, or a strftime pattern.
+    pub fn set_format(&mut self, spec: &str) -> Result<()> {
+        if let Some(precision) = Precision::from_str(spec) {
+            self.precision = precision;
+            self.pattern = None;
+        } else if spec.eq_ignore_ascii_case("iso") {
+            self.pattern = None;
+        } else {
+            // chrono panics when formatting with an invalid pattern
+            if StrftimeItems::new(spec).any(|item| matches!(item, Item::Error)) {
+                return Err(anyhow!("invalid timestamp format '{}'", spec));
+            }
+            self.pattern = Some(spec.to_string());
+        }
+        Ok(())
+    }
+
+    pub fn format(&self, ts: &NaiveDateTime) -> String {
+        let local = ts.and_utc().with_timezone(&self.timezone);
+        if let Some(pattern) = &self.pattern {
+            return local.format(pattern).to_string();
+        }
+        let fraction = match self.precision {
+            Precision::Seconds => "",
+            Precision::Millis => "%.3f",
+            Precision::Micros => "%.6f",
+            Precision::Nanos => "%.9f",
+        };
+        let offset = if self.timezone == Tz::UTC { "Z" } else { "%:z" };
+        local
+            .format(&format!("%Y-%m-%dT%H:%M:%S{}{}", fraction, offset))
+            .to_string()
+    }
+}
+
+impl fmt::Display for TimestampFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.pattern {
+            Some(pattern) => write!(f, "'{}' in {}", pattern, self.timezone),
+            None => write!(f, "ISO 8601 ({}) in {}", self.precision, self.timezone),
+        }
+    }
+}
+
+/// Decodes every column of a PGWire row.
+pub fn decode_row(row: &Row) -> Result<Vec<Value>> {
+    row.columns()
+        .iter()
+        .enumerate()
+        .map(|(i, column)| {
+            Value::decode(column.type_(), decode::raw_column(row, i)?)
+                .map_err(|e| anyhow!("column '{}': {}", column.name(), e))
+        })
+        .collect()
+}